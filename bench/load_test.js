@@ -1,5 +1,6 @@
 import http from 'k6/http';
 import { check, sleep } from 'k6';
+import encoding from 'k6/encoding';
 
 // ---------- OPTIONS ----------
 export const options = {
@@ -23,23 +24,31 @@ export const options = {
 
 // ---------- CONSTANTS ----------
 const BASE = 'https://www.kazeapi.uk';
-const AUTH = { Authorization: 'Bearer dummy_token' };   // adjust if you wire real JWT
+const HOUSING_ID = '00000000-0000-4000-8000-000000000001';
+
+// Tokens of the local issuer, one per line, minted by the server:
+//   cargo run --features dev -- test-token $(for i in $(seq 1000); do uuidgen; done) > bench/tokens.txt
+const USERS = open(__ENV.TOKENS_FILE || './tokens.txt')
+    .split('\n')
+    .filter(token => token.length > 0)
+    .map(token => ({
+        id: JSON.parse(encoding.b64decode(token.split('.')[1], 'rawurl', 's')).sub,
+        token,
+    }));
 
 // ---------- VU CODE ----------
 export default function () {
-    // Map iteration → landlordN (1-based, cycles every 1000)
-    const n = Math.floor(Math.random() * 1000) + 1;
+    const user = USERS[Math.floor(Math.random() * USERS.length)];
     const payload = JSON.stringify({
-        tenant_id: `landlord${n}`,
-        landlord_id: `landlord${n}`,
-        housing_id: 'housing1',
-        _uid: `landlord${n}`
+        tenant_id: user.id,
+        landlord_id: user.id,
+        housing_id: HOUSING_ID,
     });
 
     const res = http.post(
         `${BASE}/agreement/generate`,
         payload,
-        { headers: { ...AUTH, 'Content-Type': 'application/json' } },    // JSON body pattern :contentReference[oaicite:4]{index=4}
+        { headers: { Authorization: `Bearer ${user.token}`, 'Content-Type': 'application/json' } },    // JSON body pattern :contentReference[oaicite:4]{index=4}
     );
 
     check(res, { '200 OK': r => r.status === 200 });
@@ -20,7 +20,7 @@ def read_second_column(file_path):
 
 def main():
     base_dirs = [f"{i}.0" for i in range(1, 31)]
-    request_types = ["sharing", "signing", "generate"]
+    request_types = ["generate"]
 
     means = []
     stds = []
@@ -8,6 +8,9 @@ fn main() {
     // 2) Tellinng Cargo which library to link (`libeuscp.so` in my case)
     println!("cargo:rustc-link-lib=dylib=euscp");
 
+    // The migrations are embedded with `sqlx::migrate!`, which doesn't track new files by itself
+    println!("cargo:rerun-if-changed=deploy/db/migrations");
+
     // 3) Configuring bindgen
     let builder = bindgen::Builder::default()
         // The header that declares EULoad, EUUnload, EUGetInterface, etc.
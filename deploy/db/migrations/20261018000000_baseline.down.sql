@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS amendments;
+DROP TABLE IF EXISTS agreement_events;
+DROP TABLE IF EXISTS agreement_numbers;
+DROP TABLE IF EXISTS agreements;
+DROP TABLE IF EXISTS document_units;
+DROP TYPE IF EXISTS agreement_state;
@@ -0,0 +1,150 @@
+-- The schema that `db::setup_db` used to create on every start.
+--
+-- Every statement is idempotent, so that a database created by `setup_db`
+-- is adopted as is and only gets the migrations table.
+
+-- The state of an agreement or an amendment, see `AgreementState`
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'agreement_state') THEN
+        CREATE TYPE agreement_state AS ENUM (
+            'not_initiated', 'initiated', 'rejected',
+            'generated', 'half_signed', 'signed', 'expired', 'terminated'
+        );
+    END IF;
+END$$;
+
+-- Agreements ended before their end date by a termination act
+ALTER TYPE agreement_state ADD VALUE IF NOT EXISTS 'terminated';
+
+-- Documents of the users shared through Diia
+CREATE TABLE IF NOT EXISTS document_units (
+    user_id           TEXT PRIMARY KEY,
+    taxpayer_card     JSONB NOT NULL,
+    internal_passport JSONB NOT NULL,
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS agreements (
+    tenant_id          UUID NOT NULL,
+    landlord_id        UUID NOT NULL,
+    housing_id         UUID NOT NULL,
+    date               DATE NOT NULL DEFAULT NOW(),
+
+    state              agreement_state NOT NULL DEFAULT 'not_initiated',
+    action_by          UUID,
+    half_signature     TEXT,
+    tenant_signature   TEXT,
+    landlord_signature TEXT,
+
+    PRIMARY KEY (tenant_id, landlord_id, housing_id, date),
+
+    CHECK (state <> 'initiated'    OR action_by IS NOT NULL),
+    CHECK (state <> 'rejected'     OR action_by IS NOT NULL),
+    CHECK (state <> 'half_signed'  OR (action_by IS NOT NULL AND half_signature IS NOT NULL)),
+    CHECK (state <> 'signed'       OR (tenant_signature IS NOT NULL AND landlord_signature IS NOT NULL)),
+    CHECK (state <> 'expired'      OR (tenant_signature IS NOT NULL AND landlord_signature IS NOT NULL))
+);
+
+-- Moments of every state transition, used by `/agreement/status`
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS initiated_at     TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS rejected_at      TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS generated_at     TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS half_signed_at   TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS signed_at        TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS expired_at       TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS terminated_at    TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS rejection_reason TEXT;
+
+-- The date of the agreement between the same parties this one renews
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS renewal_of DATE;
+
+-- The last contract number allocated to each landlord
+CREATE TABLE IF NOT EXISTS agreement_numbers (
+    landlord_id UUID PRIMARY KEY,
+    last_number INTEGER NOT NULL
+);
+
+-- The contract number, allocated when the agreement is rendered for the first time
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS number INTEGER;
+
+CREATE UNIQUE INDEX IF NOT EXISTS agreements_landlord_number_idx
+    ON agreements (landlord_id, number);
+
+-- The identifier of the agreement outside of the database: in S3 keys, routes and Diia requests
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS id UUID NOT NULL DEFAULT gen_random_uuid();
+
+CREATE UNIQUE INDEX IF NOT EXISTS agreements_id_idx
+    ON agreements (id);
+
+-- The template the PDF was rendered with, to re-render it exactly
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS template_id      TEXT,
+    ADD COLUMN IF NOT EXISTS template_version INTEGER;
+
+-- Everything the PDF was rendered from, see `AgreementInputs`
+ALTER TABLE agreements
+    ADD COLUMN IF NOT EXISTS inputs         JSONB,
+    ADD COLUMN IF NOT EXISTS inputs_version INTEGER;
+
+-- Audit log of every agreement state transition
+CREATE TABLE IF NOT EXISTS agreement_events (
+    id          BIGSERIAL PRIMARY KEY,
+    tenant_id   UUID NOT NULL,
+    landlord_id UUID NOT NULL,
+    housing_id  UUID NOT NULL,
+    date        DATE NOT NULL,
+    from_state  agreement_state NOT NULL,
+    to_state    agreement_state NOT NULL,
+    action      TEXT NOT NULL,
+    action_by   UUID,
+    details     JSONB,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Amendments (addenda) to signed agreements, numbered within the agreement
+CREATE TABLE IF NOT EXISTS amendments (
+    tenant_id          UUID NOT NULL,
+    landlord_id        UUID NOT NULL,
+    housing_id         UUID NOT NULL,
+    date               DATE NOT NULL,
+    number             INTEGER NOT NULL,
+
+    state              agreement_state NOT NULL DEFAULT 'initiated',
+    action_by          UUID,
+    half_signature     TEXT,
+    tenant_signature   TEXT,
+    landlord_signature TEXT,
+    rejection_reason   TEXT,
+
+    template_id        TEXT,
+    template_version   INTEGER,
+    changes            JSONB,
+
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    rejected_at        TIMESTAMPTZ,
+    generated_at       TIMESTAMPTZ,
+    half_signed_at     TIMESTAMPTZ,
+    signed_at          TIMESTAMPTZ,
+
+    PRIMARY KEY (tenant_id, landlord_id, housing_id, date, number),
+    FOREIGN KEY (tenant_id, landlord_id, housing_id, date)
+        REFERENCES agreements (tenant_id, landlord_id, housing_id, date)
+        ON DELETE CASCADE,
+
+    CHECK (state <> 'half_signed' OR (action_by IS NOT NULL AND half_signature IS NOT NULL)),
+    CHECK (state <> 'signed'      OR (tenant_signature IS NOT NULL AND landlord_signature IS NOT NULL))
+);
+
+-- Transitions of amendments are logged along with the ones of their agreements
+ALTER TABLE agreement_events
+    ADD COLUMN IF NOT EXISTS amendment INTEGER;
+
+-- Termination acts are kept along with the amendments, see `AmendmentKind`
+ALTER TABLE amendments
+    ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'amendment';
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS diia_requests;
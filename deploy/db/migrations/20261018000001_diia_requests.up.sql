@@ -0,0 +1,22 @@
+-- Requests sent to Diia, which the callbacks answering them are matched against.
+--
+-- The request id is a random token, only its SHA-256 is kept.
+-- A token is deleted by the callback that uses it, so it can't be replayed.
+CREATE TABLE diia_requests (
+    token_hash   BYTEA PRIMARY KEY,
+    kind         TEXT NOT NULL CHECK (kind IN ('sharing', 'signature')),
+    user_id      UUID NOT NULL,
+
+    -- What is being signed, for the signature requests
+    agreement_id UUID REFERENCES agreements (id) ON DELETE CASCADE,
+    amendment    INTEGER,
+    -- The SHA-256 of the PDF sent to be signed
+    file_hash    BYTEA,
+
+    expires_at   TIMESTAMPTZ NOT NULL,
+
+    CHECK (kind <> 'signature' OR (agreement_id IS NOT NULL AND file_hash IS NOT NULL))
+);
+
+CREATE INDEX diia_requests_expires_at_idx
+    ON diia_requests (expires_at);
@@ -0,0 +1,7 @@
+ALTER TABLE amendments
+    DROP COLUMN IF EXISTS tenant_signature_report,
+    DROP COLUMN IF EXISTS landlord_signature_report;
+
+ALTER TABLE agreements
+    DROP COLUMN IF EXISTS tenant_signature_report,
+    DROP COLUMN IF EXISTS landlord_signature_report;
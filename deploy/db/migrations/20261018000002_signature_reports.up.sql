@@ -0,0 +1,9 @@
+-- What was established about each signature when it arrived: the signed hash,
+-- the certificate of the signer and when it was checked by OCSP.
+ALTER TABLE agreements
+    ADD COLUMN tenant_signature_report   JSONB,
+    ADD COLUMN landlord_signature_report JSONB;
+
+ALTER TABLE amendments
+    ADD COLUMN tenant_signature_report   JSONB,
+    ADD COLUMN landlord_signature_report JSONB;
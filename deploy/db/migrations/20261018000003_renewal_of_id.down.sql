@@ -0,0 +1,10 @@
+ALTER TABLE agreements
+    ADD COLUMN renewal_of_date DATE;
+
+UPDATE agreements a
+SET renewal_of_date = p.date
+FROM agreements p
+WHERE p.id = a.renewal_of;
+
+ALTER TABLE agreements DROP COLUMN renewal_of;
+ALTER TABLE agreements RENAME COLUMN renewal_of_date TO renewal_of;
@@ -0,0 +1,14 @@
+-- The agreement this one renews, by its id rather than by its date
+ALTER TABLE agreements
+    ADD COLUMN renewal_of_id UUID REFERENCES agreements (id) ON DELETE SET NULL;
+
+UPDATE agreements a
+SET renewal_of_id = p.id
+FROM agreements p
+WHERE p.tenant_id = a.tenant_id
+  AND p.landlord_id = a.landlord_id
+  AND p.housing_id = a.housing_id
+  AND p.date = a.renewal_of;
+
+ALTER TABLE agreements DROP COLUMN renewal_of;
+ALTER TABLE agreements RENAME COLUMN renewal_of_id TO renewal_of;
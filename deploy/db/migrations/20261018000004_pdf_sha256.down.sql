@@ -0,0 +1,5 @@
+ALTER TABLE amendments
+    DROP COLUMN IF EXISTS pdf_sha256;
+
+ALTER TABLE agreements
+    DROP COLUMN IF EXISTS pdf_sha256;
@@ -0,0 +1,6 @@
+-- The SHA-256 of the rendered PDF, the only file the parties' signatures are accepted for
+ALTER TABLE agreements
+    ADD COLUMN pdf_sha256 BYTEA;
+
+ALTER TABLE amendments
+    ADD COLUMN pdf_sha256 BYTEA;
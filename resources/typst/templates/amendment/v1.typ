@@ -0,0 +1,138 @@
+//////////////////////////////////////////////////
+//                   FUNCTIONS                  //
+//////////////////////////////////////////////////
+
+#let currency_dative_case(currency: str) = [
+  #if currency == "USD" {
+    return "доларів США"
+  } else if currency == "EUR" {
+    return "євро"
+  } else {
+    return "гривень"
+  }
+]
+
+#let to_date(date) = datetime(..date)
+
+
+//////////////////////////////////////////////////
+//                   VARIABLES                  //
+//////////////////////////////////////////////////
+
+#let font-size = (
+  text: 12pt,
+  heading: 14pt
+)
+
+
+//////////////////////////////////////////////////
+//                   DOCUMENT                   //
+//////////////////////////////////////////////////
+
+#let data = json("/data.json")
+
+#set document(
+  title: "Додаткова угода до Договору оренди нерухомого майна",
+  author: data.document.author,
+  keywords: data.document.keywords,
+)
+
+
+//////////////////////////////////////////////////
+//                     PAGE                     //
+//////////////////////////////////////////////////
+
+#set page(
+  paper: "a4",
+  margin: (
+    x: 1.8cm,
+    y: 1.5cm
+  ),
+  numbering: "1",
+)
+
+
+//////////////////////////////////////////////////
+//                     TEXT                     //
+//////////////////////////////////////////////////
+
+#set text(
+  size: font-size.text,
+  lang: "uk",
+  region: "ua"
+)
+
+#set par(
+  leading: 1.1em,
+  justify: true,
+  spacing: 2em
+)
+
+
+//////////////////////////////////////////////////
+//                   CONTENT                    //
+//////////////////////////////////////////////////
+
+#align(
+  center,
+  text(
+    size: 17pt,
+    weight: "bold",
+    [Додаткова угода №#data.number]
+  )
+)
+
+#align(
+  center,
+  text(
+    size: font-size.heading,
+    [до Договору оренди нерухомого майна від #to_date(data.agreement_date).display("[day].[month].[year]")]
+  )
+)
+
+#grid(
+  columns: (1fr, 1fr),
+  align: (left, right),
+  [м. #data.place],
+  to_date(data.date).display("[day].[month].[year]")
+)
+
+*#data.landlord_initials* (далі — Орендодавець) та *#data.tenant_initials* (далі — Орендар), разом — Сторони, уклали цю Додаткову угоду до Договору оренди нерухомого майна (далі — Договір) про таке:
+
+#let changes = data.changes
+
++ Сторони домовились внести до Договору такі зміни:
+  #if changes.price != none [
+    - розмір орендної плати становить #changes.price.amount #currency_dative_case(currency: changes.price.currency) на місяць;
+  ]
+  #if changes.ending_date != none [
+    - строк дії Договору встановлюється до #to_date(changes.ending_date).display("[day].[month].[year]");
+  ]
+  #if changes.additional_tenants != none [
+    - разом з Орендарем у житлі проживають: #if changes.additional_tenants.len() > 0 { changes.additional_tenants.join(", ") } else [ніхто];
+  ]
+  #if changes.allowed_animals != none [
+    - дозволене утримання тварин: #if changes.allowed_animals.len() > 0 { changes.allowed_animals.join(", ") } else [не дозволено];
+  ]
++ Усі інші умови Договору, не змінені цією Додатковою угодою, залишаються без змін, і Сторони підтверджують щодо них свої зобов'язання.
++ Ця Додаткова угода набирає чинності з моменту її підписання Сторонами та є невід'ємною частиною Договору.
++ Ця Додаткова угода укладена в електронній формі та підписана кваліфікованими електронними підписами Сторін.
+
+
+//////////////////////////////////////////////////
+//                  SIGNATURES                  //
+//////////////////////////////////////////////////
+
+#v(2em)
+
+#grid(
+  columns: (1fr, 1fr),
+  row-gutter: 1.5em,
+  align: (left, right),
+  [*Орендодавець*],
+  [*Орендар*],
+  [#data.landlord_initials],
+  [#data.tenant_initials],
+  [*\_\_\_\_\_\_\_\_\_\_ (Підпис)*],
+  [*\_\_\_\_\_\_\_\_\_\_ (Підпис)*],
+)
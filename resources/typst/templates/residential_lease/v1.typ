@@ -543,3 +543,65 @@
     ],
   )
 ]
+
+//////////////////////////////////////////////////
+//                     BODY                     //
+//////////////////////////////////////////////////
+
+// The agreement data is provided by the backend as a virtual file.
+#let data = json("/data.json")
+
+// Dates come as `(day: .., month: .., year: ..)` dictionaries.
+#let to_date(date) = datetime(..date)
+
+#rental_agreement_title(..data.rental_agreement_title)
+
+#rental_agreement_place_and_date(
+  place: data.rental_agreement_place_and_date.place,
+  date: to_date(data.rental_agreement_place_and_date.date),
+)
+
+#sides_of_agreement(..data.sides_of_agreement)
+
+#let ownership_record = data.subject_of_agreement.ownership_record
+#subject_of_agreement(
+  real_estate_data: data.subject_of_agreement.real_estate_data,
+  ownership_record: ownership_record + (date: to_date(ownership_record.date)),
+)
+
+#rights_and_obligations(..data.rights_and_obligations)
+
+#let rental_payment_data = data.rental_payment.rental_payment_data
+#rental_payment(
+  rental_payment_data: rental_payment_data + (
+    starting_date: to_date(rental_payment_data.starting_date),
+  ),
+)
+
+#let agreement_conditions_data = data.agreement_conditions.agreement_conditions_data
+#agreement_conditions(
+  agreement_conditions_data: (
+    starting_date: to_date(agreement_conditions_data.starting_date),
+    ending_date: to_date(agreement_conditions_data.ending_date),
+  ),
+)
+
+#responsibility()
+
+#other_conditions(..data.other_conditions)
+
+#signatures(..data.signatures)
+
+#let appendix_one_data = data.appendix_one.appendix_one_data
+#appendix_one(
+  appendix_one_data: appendix_one_data + (
+    starting_date: to_date(appendix_one_data.starting_date),
+  ),
+)
+
+#let appendix_two_data = data.appendix_two.appendix_two_data
+#appendix_two(
+  appendix_two_data: appendix_two_data + (
+    starting_date: to_date(appendix_two_data.starting_date),
+  ),
+)
@@ -0,0 +1,116 @@
+//////////////////////////////////////////////////
+//                   FUNCTIONS                  //
+//////////////////////////////////////////////////
+
+#let to_date(date) = datetime(..date)
+
+
+//////////////////////////////////////////////////
+//                   VARIABLES                  //
+//////////////////////////////////////////////////
+
+#let font-size = (
+  text: 12pt,
+  heading: 14pt
+)
+
+
+//////////////////////////////////////////////////
+//                   DOCUMENT                   //
+//////////////////////////////////////////////////
+
+#let data = json("/data.json")
+
+#set document(
+  title: "Угода про дострокове розірвання Договору оренди нерухомого майна",
+  author: data.document.author,
+  keywords: data.document.keywords,
+)
+
+
+//////////////////////////////////////////////////
+//                     PAGE                     //
+//////////////////////////////////////////////////
+
+#set page(
+  paper: "a4",
+  margin: (
+    x: 1.8cm,
+    y: 1.5cm
+  ),
+  numbering: "1",
+)
+
+
+//////////////////////////////////////////////////
+//                     TEXT                     //
+//////////////////////////////////////////////////
+
+#set text(
+  size: font-size.text,
+  lang: "uk",
+  region: "ua"
+)
+
+#set par(
+  leading: 1.1em,
+  justify: true,
+  spacing: 2em
+)
+
+
+//////////////////////////////////////////////////
+//                   CONTENT                    //
+//////////////////////////////////////////////////
+
+#align(
+  center,
+  text(
+    size: 17pt,
+    weight: "bold",
+    [Додаткова угода №#data.number]
+  )
+)
+
+#align(
+  center,
+  text(
+    size: font-size.heading,
+    [про дострокове розірвання Договору оренди нерухомого майна від #to_date(data.agreement_date).display("[day].[month].[year]")]
+  )
+)
+
+#grid(
+  columns: (1fr, 1fr),
+  align: (left, right),
+  [м. #data.place],
+  to_date(data.date).display("[day].[month].[year]")
+)
+
+*#data.landlord_initials* (далі — Орендодавець) та *#data.tenant_initials* (далі — Орендар), разом — Сторони, уклали цю Додаткову угоду до Договору оренди нерухомого майна (далі — Договір) про таке:
+
++ Сторони за взаємною згодою домовились достроково розірвати Договір. Договір припиняє свою дію #to_date(data.termination_date).display("[day].[month].[year]").
++ До дати припинення Договору Орендар звільняє Об'єкт нерухомості та повертає його Орендодавцю за Актом прийому-передачі у стані, в якому його було отримано, з урахуванням природного зносу.
++ Сторони зобов'язуються до дати припинення Договору провести взаємні розрахунки за орендною платою та комунальними послугами. Депозитна сума повертається Орендарю в порядку, встановленому Договором.
++ Після припинення Договору Сторони не мають одна до одної жодних претензій, крім тих, що стосуються зобов'язань, не виконаних до дати його припинення.
++ Ця Додаткова угода набирає чинності з моменту її підписання Сторонами та є невід'ємною частиною Договору.
++ Ця Додаткова угода укладена в електронній формі та підписана кваліфікованими електронними підписами Сторін.
+
+
+//////////////////////////////////////////////////
+//                  SIGNATURES                  //
+//////////////////////////////////////////////////
+
+#v(2em)
+
+#grid(
+  columns: (1fr, 1fr),
+  row-gutter: 1.5em,
+  align: (left, right),
+  [*Орендодавець*],
+  [*Орендар*],
+  [#data.landlord_initials],
+  [#data.tenant_initials],
+  [*\_\_\_\_\_\_\_\_\_\_ (Підпис)*],
+  [*\_\_\_\_\_\_\_\_\_\_ (Підпис)*],
+)
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use aws_config::{BehaviorVersion, Region};
+use clap::{Parser, Subcommand};
+use sqlx::migrate::Migrate;
+use tokio::runtime::Runtime;
+use tracing::{info, warn};
+
+use super::server::connect_db;
+use crate::utils::{
+    db::{DbPool, MIGRATOR},
+    server_error::ServerError,
+};
+
+#[derive(Parser, Clone)]
+#[command(version = "1.0", about = "Manages the database migrations.")]
+pub struct MigrateSubcommand {
+    /// The region on which the AWS is running.
+    #[arg(long, default_value_t = String::from("eu-central-1"))]
+    region: String,
+
+    /// The name of the secret containing database credentials
+    #[arg(long, default_value_t = String::from("rds!db-8dd73543-9c21-4891-9424-1571fc376941"))]
+    db_secret_name: String,
+
+    /// Connects to this database instead of the one from the secret, e.g. a local one.
+    #[arg(long)]
+    database_url: Option<String>,
+
+    #[command(subcommand)]
+    pub action: MigrateAction,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum MigrateAction {
+    /// Applies the pending migrations, the same as the server does on start.
+    Up,
+    /// Reverts the latest applied migration.
+    Down,
+    /// Lists the migrations along with whether they are applied.
+    Status,
+}
+
+/// Runs the migrations from `deploy/db/migrations` embedded into the binary.
+pub fn run(
+    MigrateSubcommand {
+        region,
+        db_secret_name,
+        database_url,
+        action,
+    }: MigrateSubcommand,
+) -> Result<(), ServerError> {
+    let runtime = Runtime::new()?;
+
+    tracing_subscriber::fmt().with_ansi(false).init();
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .expect("Failed to install rustls crypto provider");
+
+    runtime.block_on(async {
+        let db_pool = match database_url {
+            Some(url) => DbPool::connect(&url)
+                .await
+                .context("Failed to connect to the database")?,
+            None => {
+                let aws_config = aws_config::defaults(BehaviorVersion::latest())
+                    .region(Region::new(region))
+                    .load()
+                    .await;
+                let aws_sm_client = aws_sdk_secretsmanager::Client::new(&aws_config);
+                connect_db(&aws_sm_client, &db_secret_name).await?
+            }
+        };
+
+        match action {
+            MigrateAction::Up => {
+                MIGRATOR
+                    .run(&db_pool)
+                    .await
+                    .context("Failed to run the database migrations")?;
+                info!("The database is up to date.");
+            }
+            MigrateAction::Down => {
+                let applied = applied_migrations(&db_pool).await?;
+                let mut versions = applied.keys().rev();
+
+                let Some(latest) = versions.next() else {
+                    info!("No migrations are applied.");
+                    return Ok(());
+                };
+                let target = versions.next().copied().unwrap_or(0);
+
+                MIGRATOR
+                    .undo(&db_pool, target)
+                    .await
+                    .context("Failed to revert the database migration")?;
+                info!("Reverted the migration {latest}.");
+            }
+            MigrateAction::Status => {
+                let applied = applied_migrations(&db_pool).await?;
+
+                for migration in MIGRATOR
+                    .iter()
+                    .filter(|m| !m.migration_type.is_down_migration())
+                {
+                    let status = match applied.get(&migration.version) {
+                        None => "pending",
+                        Some(checksum) if *checksum == *migration.checksum => "applied",
+                        Some(_) => "applied, checksum mismatch",
+                    };
+                    info!("{} {}: {status}", migration.version, migration.description);
+                }
+
+                for version in applied.keys().filter(|v| !MIGRATOR.version_exists(**v)) {
+                    warn!("{version}: applied, but missing from the binary");
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// The versions of the applied migrations along with their checksums.
+async fn applied_migrations(pool: &DbPool) -> Result<BTreeMap<i64, Vec<u8>>, ServerError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect())
+}
@@ -1,10 +1,18 @@
 #![allow(dead_code)]
 
+pub mod migrate;
+pub mod packages;
 pub mod server;
+#[cfg(feature = "dev")]
+pub mod test_token;
 
 pub use super::*;
 use clap::Parser;
+use migrate::MigrateSubcommand;
+use packages::PackagesSubcommand;
 use server::ServerSubcommand;
+#[cfg(feature = "dev")]
+use test_token::TestTokenSubcommand;
 use tracing::{error, info};
 
 #[derive(Parser)]
@@ -18,6 +26,10 @@ pub struct Cli {
 #[derive(Parser)]
 pub enum Subcommands {
     Server(ServerSubcommand),
+    Packages(PackagesSubcommand),
+    Migrate(MigrateSubcommand),
+    #[cfg(feature = "dev")]
+    TestToken(TestTokenSubcommand),
 }
 
 impl Subcommands {
@@ -35,6 +47,25 @@ impl Subcommands {
                     }
                 }
             }
+            Subcommands::Packages(command) => {
+                if let Err(e) = packages::run(command) {
+                    error!("The packages command returned the error: {e:?}");
+                    std::process::exit(1);
+                }
+            }
+            Subcommands::Migrate(command) => {
+                if let Err(e) = migrate::run(command) {
+                    error!("The migrate command returned the error: {e:?}");
+                    std::process::exit(1);
+                }
+            }
+            #[cfg(feature = "dev")]
+            Subcommands::TestToken(command) => {
+                if let Err(e) = test_token::run(command) {
+                    error!("The test-token command returned the error: {e:?}");
+                    std::process::exit(1);
+                }
+            }
         }
     }
 }
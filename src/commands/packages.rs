@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use typst::syntax::package::PackageSpec;
+
+use crate::utils::{
+    config::Config,
+    server_error::ServerError,
+    typst::{download_package_archive, package_subdir, unpack_package_archive},
+};
+
+/// The name of the manifest inside the packages directory.
+pub const MANIFEST_FILE_NAME: &str = "packages.toml";
+
+/// The list of the vendored packages.
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(rename = "package", default)]
+    packages: Vec<ManifestEntry>,
+}
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    /// The package, e.g. `@preview/tablex:0.0.9`.
+    spec: String,
+    /// The SHA-256 of the `.tar.gz` archive of the package.
+    sha256: String,
+}
+
+#[derive(Parser, Clone)]
+#[command(version = "1.0", about = "Manages the vendored Typst packages.")]
+pub struct PackagesSubcommand {
+    /// A path to the config file.
+    #[arg(long, default_value_t = String::from("./config.toml"))]
+    pub config_path: String,
+
+    #[command(subcommand)]
+    pub action: PackagesAction,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PackagesAction {
+    /// Downloads the missing packages from the manifest and unpacks them.
+    Fetch,
+    /// Checks that every package from the manifest is vendored, matches its checksum
+    /// and is unpacked exactly as in its archive.
+    Verify,
+}
+
+/// Pre-populates or verifies the packages directory from `typst.packages_dir`.
+///
+/// The directory contains `packages.toml` with the list of the packages.
+/// Each package is kept both as the original archive, `<namespace>/<name>/<version>.tar.gz`,
+/// and unpacked, `<namespace>/<name>/<version>`, which is what the renderer reads.
+pub fn run(
+    PackagesSubcommand {
+        config_path,
+        action,
+    }: PackagesSubcommand,
+) -> Result<(), ServerError> {
+    tracing_subscriber::fmt().with_ansi(false).init();
+
+    let config = Config::new(&config_path);
+    let packages_dir = PathBuf::from(
+        config
+            .typst
+            .packages_dir
+            .ok_or_else(|| anyhow!("`typst.packages_dir` is not set in the config"))?,
+    );
+
+    let manifest_path = packages_dir.join(MANIFEST_FILE_NAME);
+    let manifest: Manifest = toml::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("cannot read {}", manifest_path.display()))?,
+    )?;
+
+    let http = ureq::Agent::new_with_defaults();
+
+    for entry in manifest.packages {
+        let package: PackageSpec = entry
+            .spec
+            .parse()
+            .map_err(|e| anyhow!("invalid package `{}`: {e}", entry.spec))?;
+
+        let subdir = package_subdir(&package);
+        let archive_path = packages_dir.join(format!("{subdir}.tar.gz"));
+        let unpacked_path = packages_dir.join(&subdir);
+
+        let archive = match (std::fs::read(&archive_path), &action) {
+            (Ok(archive), _) => archive,
+            (Err(_), PackagesAction::Fetch) => {
+                download_package_archive(&http, &package).map_err(|e| anyhow!("{e}"))?
+            }
+            (Err(e), PackagesAction::Verify) => {
+                return Err(anyhow!("package {package} is not vendored: {e}").into());
+            }
+        };
+
+        let checksum = format!("{:x}", Sha256::digest(&archive));
+        if checksum != entry.sha256 {
+            return Err(anyhow!(
+                "checksum mismatch for {package}: expected {}, got {checksum}",
+                entry.sha256
+            )
+            .into());
+        }
+
+        match action {
+            PackagesAction::Fetch => {
+                if let Some(parent) = archive_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&archive_path, &archive)?;
+
+                // always unpacking from scratch, so that the files match the verified archive
+                if unpacked_path.exists() {
+                    std::fs::remove_dir_all(&unpacked_path)?;
+                }
+                unpack_package_archive(&archive, &unpacked_path).map_err(|e| anyhow!("{e}"))?;
+            }
+            PackagesAction::Verify => {
+                if !unpacked_path.is_dir() {
+                    return Err(anyhow!("package {package} is not unpacked").into());
+                }
+
+                // the renderer reads the unpacked files, so they are checked against
+                // a fresh copy from the verified archive
+                let expected_path = packages_dir.join(format!("{subdir}.verify"));
+                if expected_path.exists() {
+                    std::fs::remove_dir_all(&expected_path)?;
+                }
+                unpack_package_archive(&archive, &expected_path).map_err(|e| anyhow!("{e}"))?;
+                let expected = tree_checksums(&expected_path);
+                std::fs::remove_dir_all(&expected_path)?;
+
+                if tree_checksums(&unpacked_path)? != expected? {
+                    return Err(anyhow!(
+                        "package {package} doesn't match its archive, fetch it again"
+                    )
+                    .into());
+                }
+            }
+        }
+
+        info!("Package {package} is verified");
+    }
+
+    Ok(())
+}
+
+/// The SHA-256 of every file under `dir`, keyed by its path relative to `dir`.
+///
+/// A symlink is hashed by its target, so that it can't point outside unnoticed.
+fn tree_checksums(dir: &Path) -> anyhow::Result<BTreeMap<PathBuf, String>> {
+    let mut checksums = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(current) = dirs.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+
+            let contents = if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            } else if file_type.is_symlink() {
+                std::fs::read_link(&path)?
+                    .into_os_string()
+                    .into_encoded_bytes()
+            } else {
+                std::fs::read(&path)?
+            };
+
+            checksums.insert(
+                path.strip_prefix(dir)?.to_path_buf(),
+                format!("{:x}", Sha256::digest(&contents)),
+            );
+        }
+    }
+
+    Ok(checksums)
+}
@@ -1,29 +1,31 @@
 use crate::utils::cache::{build_cache, populate_cache_from_file, CACHE_SAVE_LOCATION_DEFAULT};
 use crate::utils::config::Config;
-use crate::utils::db::{init_db_pool, setup_db, DbPool};
-use crate::utils::diia::{diia_signature_handler, refresh_diia_session_token};
+use crate::utils::db::{
+    init_db_pool, run_migrations, AmendmentSignatureEntry, DbPool, SignatureEntry,
+};
+use crate::utils::diia::{
+    amendment_signature_handler, diia_signature_handler, refresh_diia_session_token,
+};
 use crate::utils::eusign::*;
+use crate::utils::expiry::expire_agreements;
 use crate::utils::secrets::get_secret;
 use crate::utils::server_error::EUSignError;
 use crate::utils::shutdown::graceful_shutdown;
+use crate::utils::templates::TemplateRegistry;
+use crate::utils::verify_jwt::TokenVerifier;
 use aws_config::{BehaviorVersion, Region};
 use axum::routing::{delete, get, post};
 use axum::Router;
 use axum_server::Handle;
 use clap::Parser;
-use rs_firebase_admin_sdk::auth::token::cache::HttpCache;
-use rs_firebase_admin_sdk::auth::token::crypto::JwtRsaPubKey;
-use rs_firebase_admin_sdk::auth::token::LiveTokenVerifier;
-use rs_firebase_admin_sdk::*;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
 use std::ffi::{c_char, CString};
 use std::net::{SocketAddr, TcpListener};
 use std::ptr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::fs::read_to_string;
 use tokio::runtime::Runtime;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::sleep;
 use tokio_util::task::LocalPoolHandle;
@@ -58,13 +60,36 @@ fn default_db_name() -> String {
     "diia".into()
 }
 
+/// Connects to the database with the credentials from the AWS secret `db_secret_name`.
+pub async fn connect_db(
+    aws_sm_client: &aws_sdk_secretsmanager::Client,
+    db_secret_name: &str,
+) -> Result<DbPool, ServerError> {
+    let db_secret = get_secret(aws_sm_client, db_secret_name)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve database secret"))?;
+
+    let db_config: DatabaseConfig = serde_json::from_str(&db_secret)
+        .map_err(|e| anyhow::anyhow!("Failed to parse database secret: {}", e))?;
+
+    init_db_pool(
+        &db_config.host,
+        db_config.port,
+        &db_config.username,
+        &db_config.password,
+        &db_config.dbname,
+    )
+    .await
+}
+
 /// A function that starts the server.
 pub fn run(
     ServerSubcommand {
         https_port,
         config_path,
         challenge_cache_update_freq,
-        agreement_template_path,
+        expiry_check_freq,
+        agreement_templates_dir,
         region,
         db_secret_name,
         s3_bucket_name,
@@ -87,30 +112,19 @@ pub fn run(
         let aws_sm_client = aws_sdk_secretsmanager::Client::new(&aws_config);
         let aws_s3_client = aws_sdk_s3::Client::new(&aws_config);
 
-        let db_secret = get_secret(&aws_sm_client, &db_secret_name)
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve database secret"))?;
-
-        let db_config: DatabaseConfig = serde_json::from_str(&db_secret)
-            .map_err(|e| anyhow::anyhow!("Failed to parse database secret: {}", e))?;
-
-        let db_pool = init_db_pool(
-            &db_config.host,
-            db_config.port,
-            &db_config.username,
-            &db_config.password,
-            &db_config.dbname,
-        )
-        .await?;
-        setup_db(&db_pool).await?;
+        let db_pool = connect_db(&aws_sm_client, &db_secret_name).await?;
+        run_migrations(&db_pool).await?;
         info!("Database connection established successfully.");
 
         let config = Config::new(&config_path);
 
-        let (signature_request_sender, mut signature_request_receiver) =
+        // the signatures are combined as soon as both arrive
+        let (signature_sender, mut signature_request_receiver) =
+            tokio::sync::mpsc::unbounded_channel();
+        let (amendment_signature_sender, mut amendment_signature_receiver) =
             tokio::sync::mpsc::unbounded_channel();
 
-        let cache = build_cache(Arc::new(db_pool.clone()), signature_request_sender);
+        let cache = build_cache();
         populate_cache_from_file(CACHE_SAVE_LOCATION_DEFAULT, &cache).await?;
 
         // cache keeper task to trigger cache updates once in a while
@@ -163,21 +177,10 @@ pub fn run(
             }
         }
 
-        let agreement_template_string = Arc::new(read_to_string(agreement_template_path).await?);
-
-        // Live Firebase App
-        let gcp_service_account = credentials_provider()
-            .await
-            .expect("cannot receive google service account");
-        let live_app = App::live(gcp_service_account)
-            .await
-            .expect("cannot receive google live app");
-        let live_token_verifier = Arc::new(
-            live_app
-                .id_token_verifier()
-                .await
-                .expect("cannot receive google live token verifier"),
-        );
+        let templates =
+            Arc::new(TemplateRegistry::load(&agreement_templates_dir, config.typst.clone()).await?);
+
+        let token_verifier = Arc::new(TokenVerifier::new(&config.auth).await?);
 
         // Cache cloning is cheap, hence using state instead of an extension.
         let server_state = ServerState {
@@ -186,14 +189,33 @@ pub fn run(
             signature_cert: Arc::new(signature_cert),
             cache,
             db_pool,
-            agreement_template_string,
-            live_token_verifier,
+            templates,
+            token_verifier,
             aws_sm_client,
             aws_s3_client,
             s3_bucket_name,
             diia_session_token: Arc::new(Mutex::new("".into())),
+            signature_sender,
+            amendment_signature_sender,
         };
 
+        // expiry keeper task to end the agreements that reached their end date
+        let cloned_server_state = server_state.clone();
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(expiry_check_freq);
+            loop {
+                timer.tick().await;
+                if let Err(e) = expire_agreements(
+                    &cloned_server_state.db_pool,
+                    &cloned_server_state.config.notifications,
+                )
+                .await
+                {
+                    error!("couldn't check the agreements for expiry: {:?}", e);
+                }
+            }
+        });
+
         // Setting up cache renewal for Diia once per every ~two hours
         let cloned_server_state = server_state.clone();
         tokio::spawn(async move {
@@ -215,12 +237,23 @@ pub fn run(
         let cloned_server_state = server_state.clone();
         pool.spawn_pinned(|| async move {
             loop {
-                if let Some(signature_entry) = signature_request_receiver.recv().await {
-                    if let Err(e) =
-                        diia_signature_handler(cloned_server_state.clone(), signature_entry).await
-                    {
-                        error!("couldn't handle signature reqeust: {:?}", e);
+                tokio::select! {
+                    Some(signature_entry) = signature_request_receiver.recv() => {
+                        if let Err(e) =
+                            diia_signature_handler(cloned_server_state.clone(), signature_entry).await
+                        {
+                            error!("couldn't handle signature reqeust: {:?}", e);
+                        }
                     }
+                    Some(signature_entry) = amendment_signature_receiver.recv() => {
+                        if let Err(e) =
+                            amendment_signature_handler(cloned_server_state.clone(), signature_entry)
+                                .await
+                        {
+                            error!("couldn't handle amendment signature request: {:?}", e);
+                        }
+                    }
+                    else => break,
                 }
             }
         });
@@ -271,10 +304,46 @@ pub fn run(
                 "/agreement/status",
                 get(crate::routes::agreement::status::handler),
             )
+            .route(
+                "/agreement/reject",
+                post(crate::routes::agreement::reject::handler),
+            )
+            .route(
+                "/agreement/terminate",
+                post(crate::routes::agreement::terminate::handler),
+            )
+            .route(
+                "/agreement/find",
+                get(crate::routes::agreement::find::handler),
+            )
+            .route(
+                "/agreement/renew",
+                post(crate::routes::agreement::renew::handler),
+            )
             .route(
                 "/agreement/remove",
                 delete(crate::routes::agreement::remove::handler),
             )
+            .route(
+                "/amendment/create",
+                post(crate::routes::amendment::create::handler),
+            )
+            .route(
+                "/amendment/get",
+                get(crate::routes::amendment::get::handler),
+            )
+            .route(
+                "/amendment/get_signed",
+                get(crate::routes::amendment::get_signed::handler),
+            )
+            .route(
+                "/amendment/reject",
+                post(crate::routes::amendment::reject::handler),
+            )
+            .route(
+                "/admin/reload_templates",
+                post(crate::routes::admin::reload_templates::handler),
+            )
             .layer(cors)
             .with_state(server_state.clone());
 
@@ -306,11 +375,10 @@ pub struct ServerState {
     pub cache: AgreementProposalCache,
     /// The database connection pool
     pub db_pool: DbPool,
-    /// A string which contains a Typst template for the agreeement.
-    pub agreement_template_string: Arc<String>,
-    /// Firebase Token verifirer
-    pub live_token_verifier:
-        Arc<LiveTokenVerifier<HttpCache<reqwest::Client, BTreeMap<String, JwtRsaPubKey>>>>,
+    /// All versions of the Typst templates for the agreements.
+    pub templates: Arc<TemplateRegistry>,
+    /// Verifies the tokens of the users
+    pub token_verifier: Arc<TokenVerifier>,
     /// AWS SM client
     pub aws_sm_client: aws_sdk_secretsmanager::Client,
     /// AWS S3 client
@@ -319,6 +387,10 @@ pub struct ServerState {
     pub s3_bucket_name: String,
     /// Diia session token
     pub diia_session_token: Arc<Mutex<String>>,
+    /// Sends fully signed agreements to be combined into a single signed file.
+    pub signature_sender: UnboundedSender<SignatureEntry>,
+    /// Sends fully signed amendments to be combined into a single signed file.
+    pub amendment_signature_sender: UnboundedSender<AmendmentSignatureEntry>,
 }
 
 #[derive(Parser, Clone)]
@@ -335,9 +407,13 @@ pub struct ServerSubcommand {
     #[arg(long, default_value = "1000", value_parser = parse_duration)]
     challenge_cache_update_freq: Duration,
 
-    /// A path to the config file.
-    #[arg(long, default_value_t = String::from("./resources/typst/rental_agreement_template.typ"))]
-    pub agreement_template_path: String,
+    /// How often the agreements are checked for reaching their end, in milliseconds.
+    #[arg(long, default_value = "600000", value_parser = parse_duration)]
+    expiry_check_freq: Duration,
+
+    /// A path to the directory with agreement templates.
+    #[arg(long, default_value_t = String::from("./resources/typst/templates"))]
+    pub agreement_templates_dir: String,
 
     /// The region on which the AWS is running.
     #[arg(long, default_value_t = String::from("eu-central-1"))]
@@ -0,0 +1,50 @@
+use anyhow::anyhow;
+use chrono::Duration;
+use clap::Parser;
+use uuid::Uuid;
+
+use crate::utils::{
+    config::{AuthConfig, Config},
+    local_issuer::LocalIssuer,
+    server_error::ServerError,
+};
+
+#[derive(Parser, Clone)]
+#[command(
+    version = "1.0",
+    about = "Mints tokens of the local issuer, accepted by a server with `auth.provider = \"local\"`."
+)]
+pub struct TestTokenSubcommand {
+    /// A path to the config file, the key is taken from its `auth` section.
+    #[arg(long, default_value_t = String::from("./config.toml"))]
+    pub config_path: String,
+
+    /// How long the tokens are valid for, in seconds.
+    #[arg(long, default_value_t = 3600)]
+    pub ttl: i64,
+
+    /// The users to mint the tokens for.
+    #[arg(required = true)]
+    pub uids: Vec<Uuid>,
+}
+
+/// Prints a token for each of the users, one per line.
+pub fn run(
+    TestTokenSubcommand {
+        config_path,
+        ttl,
+        uids,
+    }: TestTokenSubcommand,
+) -> Result<(), ServerError> {
+    let AuthConfig::Local { key_path } = Config::new(&config_path).auth else {
+        return Err(anyhow!("`auth.provider` is not \"local\" in {config_path}").into());
+    };
+
+    let issuer = LocalIssuer::load_or_generate(&key_path)?;
+
+    for uid in uids {
+        println!("{}", issuer.mint(uid, Duration::seconds(ttl))?);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use axum::extract::{Json, State};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{auth::AuthUser, server_error::ServerError},
+};
+
+#[derive(Serialize)]
+pub struct Response {
+    pub success: bool,
+}
+
+/// Reloads the agreement templates from disk without restarting the server.
+///
+/// New templates are compile-checked first and swapped in only if all of them compile.
+/// Agreements being rendered at the moment keep the templates they started with.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+) -> Result<Json<Response>, ServerError> {
+    if !state.config.admin.uids.contains(&uid) {
+        return Err(ServerError::Forbidden(
+            "you are not allowed to reload the templates".into(),
+        ));
+    }
+
+    state.templates.reload().await?;
+
+    info!("Agreement templates were reloaded by {uid}");
+
+    Ok(Json(Response { success: true }))
+}
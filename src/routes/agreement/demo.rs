@@ -1,37 +1,28 @@
-use std::sync::Arc;
-
 use anyhow::anyhow;
 use axum::{extract::State, response::Response, Json};
+use chrono::Utc;
 use http::{header, StatusCode};
 use serde::{Deserialize, Serialize};
-use typst_pdf::PdfOptions;
 
 use crate::{
     commands::server::ServerState,
     utils::{
-        agreement::{generate, HousingData, OwneshipData, RentData, RequisitesData},
-        eusign::DocumentUnit,
+        agreement::{generate, AgreementInputs, DocumentInfo},
         server_error::ServerError,
-        typst::TypstWrapperWorld,
+        templates::TemplateId,
     },
 };
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct Payload {
-    /// The housing data, like address or area.
-    #[serde(default)]
-    pub housing_data: HousingData,
-    /// The rental data, like meter readings or monthly price.
-    #[serde(default)]
-    pub rent_data: RentData,
-    #[serde(default)]
-    pub tenant: DocumentUnit,
+    /// The data of the parties, the housing and the terms.
+    #[serde(flatten)]
+    pub inputs: AgreementInputs,
+    /// The kind of the agreement, which selects the template.
     #[serde(default)]
-    pub landlord: DocumentUnit,
-    #[serde(default)]
-    pub requisites_data: RequisitesData,
-    #[serde(default)]
-    pub ownership_data: OwneshipData,
+    pub template_id: TemplateId,
+    /// The version of the template. The latest one is used if not passed.
+    pub template_version: Option<i32>,
 }
 
 /// Generates rental ageement between tenant and landlord.
@@ -39,24 +30,28 @@ pub async fn handler(
     State(state): State<ServerState>,
     Json(payload): Json<Payload>,
 ) -> Result<Response, ServerError> {
-    let typst_code = generate(
-        &state,
-        Arc::new(payload.tenant),
-        Arc::new(payload.landlord),
-        payload.housing_data,
-        payload.rent_data,
-        payload.requisites_data,
-        payload.ownership_data,
-    )
-    .await?;
+    if !payload.template_id.is_agreement() {
+        return Err(ServerError::BadRequest(format!(
+            "`{}` is not an agreement template",
+            payload.template_id
+        )));
+    }
+
+    let template = match payload.template_version {
+        Some(version) => state.templates.get(payload.template_id, version)?,
+        None => state.templates.latest(payload.template_id)?,
+    };
 
-    let world = TypstWrapperWorld::new("./".to_owned(), typst_code.to_owned());
+    let info = DocumentInfo {
+        agreement_id: "demo".into(),
+        template_id: template.id,
+        template_version: template.version,
+        created_at: Utc::now(),
+    };
 
-    let document = typst::compile(&world)
-        .output
-        .map_err(|_| anyhow!("cannot compile Typst document"))?;
+    let data = generate(payload.inputs, 1, &info)?;
 
-    let pdf = typst_pdf::pdf(&document, &PdfOptions::default()).expect("Error exporting PDF");
+    let pdf = tokio::task::spawn_blocking(move || template.render(&data, &info)).await??;
 
     let response = Response::builder()
         .status(StatusCode::OK)
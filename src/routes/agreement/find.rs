@@ -0,0 +1,48 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        auth::{require_party, AuthUser},
+        db::{self, AgreementKey},
+        server_error::ServerError,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct Payload {
+    pub landlord_id: Uuid,
+    /// The contract number among the agreements of the landlord.
+    pub number: i32,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    #[serde(flatten)]
+    pub agreement: AgreementKey,
+}
+
+/// Finds the agreement of the landlord by its contract number.
+///
+/// The agreements of other parties are reported as missing, so that the numbers
+/// in use can't be found out by trying them.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Query(payload): Query<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement =
+        db::find_agreement_by_number(&state.db_pool, payload.landlord_id, payload.number)
+            .await?
+            .filter(|agreement| {
+                require_party(uid, agreement.tenant_id, agreement.landlord_id).is_ok()
+            })
+            .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    Ok(Json(Response { agreement }))
+}
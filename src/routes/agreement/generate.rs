@@ -1,32 +1,29 @@
 use std::sync::Arc;
 
-use anyhow::anyhow;
 use axum::{extract::State, Json};
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
 use moka::ops::compute::Op;
 use serde::{Deserialize, Serialize};
-use typst_pdf::PdfOptions;
+use uuid::Uuid;
 
 use crate::{
     commands::server::ServerState,
     utils::{
-        agreement::{generate, HousingData, OwneshipData, RentData, RequisitesData},
+        agreement::{AgreementInputs, HousingData, OwneshipData, RentData, RequisitesData},
+        agreements::render_agreement,
+        auth::{require_party, AuthUser},
         cache::{AgreementProposalKey, AgreementProposalValue},
-        db, s3,
+        db::{self, AgreementAction, AgreementKey, AgreementState},
+        s3,
         server_error::ServerError,
-        typst::TypstWrapperWorld,
-        verify_jwt::verify_jwt,
+        templates::TemplateId,
     },
 };
 
 #[derive(Deserialize)]
 pub struct Payload {
-    pub tenant_id: String,
-    pub landlord_id: String,
-    pub housing_id: String,
+    pub tenant_id: Uuid,
+    pub landlord_id: Uuid,
+    pub housing_id: Uuid,
     /// The housing data, like address or area.
     #[serde(default)]
     pub housing_data: HousingData,
@@ -39,52 +36,102 @@ pub struct Payload {
     /// The ownership data, like ownership record number and date.
     #[serde(default)]
     pub ownership_data: OwneshipData,
-
-    /// This is a backdoor for testing purposes
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
+    /// The kind of the agreement, which selects the template.
+    #[serde(default)]
+    pub template_id: TemplateId,
+    /// Set to re-initiate a previously rejected proposal.
+    #[serde(default)]
+    pub reinitiate: bool,
 }
 
 #[derive(Serialize)]
-pub struct Response {}
+pub struct Response {
+    /// The agreement being proposed, to refer to it in the other routes.
+    pub agreement_id: Uuid,
+}
 
 /// Generates a rental ageement between tenant and landlord.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    AuthUser(uid): AuthUser,
     Json(payload): Json<Payload>,
 ) -> Result<Json<Response>, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
+    require_party(uid, payload.tenant_id, payload.landlord_id)?;
 
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
+    if !payload.template_id.is_agreement() {
+        return Err(ServerError::BadRequest(format!(
+            "`{}` is not an agreement template",
+            payload.template_id
+        )));
+    }
 
-    if !(uid == payload.landlord_id || uid == payload.tenant_id) {
-        return Err(anyhow!(
-            "you are not authorized to perform this action: you're not a landlord or a tenant"
-        )
-        .into());
+    // initiating the proposal in the DB,
+    // a rejected proposal stays blocked until one of the parties re-initiates it
+    let latest = db::get_agreement_state(
+        &state.db_pool,
+        payload.tenant_id,
+        payload.landlord_id,
+        payload.housing_id,
+        None,
+    )
+    .await?;
+    match latest.map(|record| record.state) {
+        Some(AgreementState::Initiated | AgreementState::Generated) => {}
+        Some(AgreementState::Rejected) if !payload.reinitiate => {
+            return Err(ServerError::Conflict(
+                "the agreement was rejected: re-initiate it first".into(),
+            ));
+        }
+        Some(AgreementState::NotInitiated | AgreementState::Rejected) => {
+            db::apply_transition(
+                &state.db_pool,
+                payload.tenant_id,
+                payload.landlord_id,
+                payload.housing_id,
+                AgreementAction::Initiate { by: uid },
+            )
+            .await?;
+        }
+        Some(AgreementState::HalfSigned) => {
+            return Err(ServerError::Conflict(
+                "the agreement is already being signed".into(),
+            ));
+        }
+        None
+        | Some(AgreementState::Signed | AgreementState::Expired | AgreementState::Terminated) => {
+            db::create_agreement(
+                &state.db_pool,
+                payload.tenant_id,
+                payload.landlord_id,
+                payload.housing_id,
+                uid,
+            )
+            .await?;
+        }
     }
 
-    let tenant_data = db::get_document_unit_from_db(&state.db_pool, &payload.tenant_id).await?;
+    let tenant_data =
+        db::get_document_unit_from_db(&state.db_pool, &payload.tenant_id.to_string()).await?;
 
-    let landlord_data = db::get_document_unit_from_db(&state.db_pool, &payload.landlord_id).await?;
+    let landlord_data =
+        db::get_document_unit_from_db(&state.db_pool, &payload.landlord_id.to_string()).await?;
+
+    let record = db::get_agreement_state(
+        &state.db_pool,
+        payload.tenant_id,
+        payload.landlord_id,
+        payload.housing_id,
+        None,
+    )
+    .await?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
 
     let result = state
         .cache
         .entry(AgreementProposalKey {
-            tenant_id: payload.tenant_id.clone(),
-            landlord_id: payload.landlord_id.clone(),
-            housing_id: payload.housing_id.clone(),
+            tenant_id: payload.tenant_id.to_string(),
+            landlord_id: payload.landlord_id.to_string(),
+            housing_id: payload.housing_id.to_string(),
         })
         .and_compute_with(|entry| {
             let op = match entry {
@@ -125,46 +172,50 @@ pub async fn handler(
         | moka::ops::compute::CompResult::ReplacedWith(entry) => {
             let val = entry.value();
             if !(val.landlord_confirmed && val.tenant_confirmed) {
-                return Ok(Json(Response {}));
+                return Ok(Json(Response {
+                    agreement_id: record.id,
+                }));
             }
         }
         _ => {
-            return Ok(Json(Response {}));
+            return Ok(Json(Response {
+                agreement_id: record.id,
+            }));
         }
     }
 
     // If we got two confirmations, actually generating a file
-    let typst_code = generate(
-        &state,
-        tenant_data,
-        landlord_data,
-        payload.housing_data,
-        payload.rent_data,
-        payload.requisites_data,
-        payload.ownership_data,
-    )
-    .await?;
+    let template = state.templates.latest(payload.template_id)?;
+    let inputs = AgreementInputs {
+        tenant: (*tenant_data).clone(),
+        landlord: (*landlord_data).clone(),
+        housing_data: payload.housing_data,
+        rent_data: payload.rent_data,
+        requisites_data: payload.requisites_data,
+        ownership_data: payload.ownership_data,
+    };
+    let key = AgreementKey {
+        id: record.id,
+        tenant_id: payload.tenant_id,
+        landlord_id: payload.landlord_id,
+        housing_id: payload.housing_id,
+        date: record.date,
+    };
 
-    let pdf = tokio::task::spawn_blocking(move || -> anyhow::Result<Vec<u8>> {
-        let world = TypstWrapperWorld::new("./".to_owned(), typst_code);
-        let document = typst::compile(&world)
-            .output
-            .map_err(|e| anyhow!("cannot compile Typst document {:?}", e))?;
-        Ok(typst_pdf::pdf(&document, &PdfOptions::default()).expect("Error exporting PDF"))
-    })
-    .await??;
-
-    // writing a file to S3 with a corresponding key
-    s3::upload_agreement_pdf(
+    let rendered = render_agreement(
         &state,
-        pdf,
-        Arc::new(AgreementProposalKey {
-            tenant_id: payload.tenant_id,
-            landlord_id: payload.landlord_id,
-            housing_id: payload.housing_id,
-        }),
+        &key,
+        &template,
+        record.transitions.created_at,
+        &serde_json::to_value(&inputs)?,
     )
     .await?;
 
-    Ok(Json(Response {}))
+    // writing a file to S3 with a corresponding key,
+    // if it fails, the agreement is rendered again by the next request
+    s3::upload_agreement_pdf(&state, rendered.pdf, record.id).await?;
+
+    Ok(Json(Response {
+        agreement_id: record.id,
+    }))
 }
@@ -3,59 +3,37 @@ use axum::{
     extract::{Query, State},
     response::Response,
 };
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
 use http::{header, StatusCode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     commands::server::ServerState,
-    utils::{s3, server_error::ServerError, verify_jwt::verify_jwt},
+    utils::{
+        auth::{require_party, AuthUser},
+        db, s3,
+        server_error::ServerError,
+    },
 };
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct Payload {
-    pub tenant_id: Uuid,
-    pub landlord_id: Uuid,
-    pub housing_id: Uuid,
-
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
+    pub agreement_id: Uuid,
 }
 
-/// Retuns the data about the latest rental ageement between tenant and landlord.
+/// Returns the PDF of the rental agreement.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    AuthUser(uid): AuthUser,
     Query(payload): Query<Payload>,
 ) -> Result<Response, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
 
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    if !(uid == payload.landlord_id || uid == payload.tenant_id) {
-        return Err(anyhow!(
-            "you are not authorized to perform this action: you're not a landlord or a tenant"
-        )
-        .into());
-    }
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
 
-    let pdf = s3::get_agreement_pdf(&state, payload.tenant_id, payload.landlord_id, payload.housing_id).await?;
+    let pdf = s3::get_agreement_pdf(&state, agreement.id).await?;
 
-    let filename = s3::get_key_for_s3(payload.tenant_id, payload.landlord_id, payload.housing_id);
+    let filename = s3::get_key_for_s3(agreement.id);
 
     let response = Response::builder()
         .status(StatusCode::OK)
@@ -3,10 +3,6 @@ use axum::{
     extract::{Query, State},
     Json,
 };
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
 use http::{
@@ -20,7 +16,11 @@ use uuid::Uuid;
 use crate::{
     commands::server::ServerState,
     utils::{
-        s3::get_agreement_pdf, server_error::ServerError, verify_jwt::verify_jwt
+        auth::{require_party, AuthUser},
+        db::{get_agreement_key, get_agreement_state, get_amendment, AgreementState, DiiaRequest},
+        diia::issue_diia_request,
+        s3::{get_agreement_pdf, get_amendment_pdf},
+        server_error::ServerError,
     },
 };
 
@@ -53,15 +53,6 @@ pub struct SignHashRequest {
     pub data: RequestData,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct SignHashRequestId {
-    pub tenant_id: Uuid,
-    pub landlord_id: Uuid,
-    pub signed_by: Uuid,
-    pub housing_id: Uuid,
-    pub seed: Uuid,
-}
-
 #[derive(Deserialize)]
 struct SignHashResponse {
     deeplink: String,
@@ -69,13 +60,9 @@ struct SignHashResponse {
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct Payload {
-    pub tenant_id: Uuid,
-    pub landlord_id: Uuid,
-    pub housing_id: Uuid,
-
-    /// This is a backdoor for testing purposes
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
+    pub agreement_id: Uuid,
+    /// Set to sign the amendment with this number instead of the agreement.
+    pub amendment: Option<i32>,
 }
 
 #[derive(Serialize)]
@@ -84,71 +71,82 @@ pub struct Response {
 }
 
 /// Generates a Diia Signature deeplink for a user.
-/// The deeplink activation through Diia app will trigger the signing of the agreement.
+/// The deeplink activation through Diia app will trigger the signing of the agreement,
+/// or of its amendment if `amendment` is set.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    AuthUser(uid): AuthUser,
     Query(payload): Query<Payload>,
 ) -> Result<Json<Response>, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
+    let agreement = get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    // checking whether the document was rendered and can be signed,
+    // a rejected proposal can't be signed until it's re-initiated
+    let record = get_agreement_state(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        Some(agreement.date),
+    )
+    .await?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    let amendment_key = payload.amendment.map(|number| agreement.amendment(number));
+
+    let (document, document_state) = match &amendment_key {
+        None => ("agreement", record.state),
+        Some(key) => {
+            let amendment = get_amendment(&state.db_pool, key)
+                .await?
+                .ok_or_else(|| ServerError::NotFound("no such amendment".into()))?;
+            ("amendment", amendment.state)
+        }
     };
-
-    if !(uid == payload.landlord_id || uid == payload.tenant_id) {
-        return Err(anyhow!(
-            "you are not authorized to perform this action: you're not a landlord or a tenant"
-        )
-        .into());
+    if !matches!(
+        document_state,
+        AgreementState::Generated | AgreementState::HalfSigned
+    ) {
+        return Err(ServerError::Conflict(format!(
+            "{document} in state `{}` can't be signed",
+            document_state.as_str()
+        )));
     }
 
-    // checking whether users confirmed the generation in DB
-    // TODO
-
     // getting the file to generate signed hash
-    let pdf = get_agreement_pdf(
-        &state,
-        payload.tenant_id.clone(),
-        payload.landlord_id.clone(),
-        payload.housing_id.clone(),
-    )
-    .await?;
-
-    // generating the hash
-    let base64_hash = {
-        let mut hasher = Sha256::new();
-        hasher.update(pdf);
-        let rust_bytes = hasher.finalize().to_vec();
-        STANDARD.encode(&rust_bytes)
+    let pdf = match &amendment_key {
+        None => get_agreement_pdf(&state, agreement.id).await?,
+        Some(key) => get_amendment_pdf(&state, key).await?,
     };
 
-    let request_id = SignHashRequestId {
-        tenant_id: payload.tenant_id,
-        landlord_id: payload.landlord_id,
-        signed_by: uid,
-        housing_id: payload.housing_id,
-        seed: Uuid::new_v4(),
-    };
+    // generating the hash
+    let file_hash = Sha256::digest(pdf).to_vec();
+    let base64_hash = STANDARD.encode(&file_hash);
+
+    // the callback is matched against the request, and its signature against the hash
+    let request_id = issue_diia_request(
+        &state.db_pool,
+        &DiiaRequest::Signature {
+            signed_by: uid,
+            agreement_id: agreement.id,
+            amendment: payload.amendment,
+            file_hash,
+        },
+    )
+    .await?;
 
     // setting up the request
     let request = SignHashRequest {
         offer_id: state.config.diia.offer_signing_id.clone(),
         return_link: "https://mykaze.org".into(),
-        request_id: serde_json::to_string(&request_id)?,
+        request_id,
         sign_algo: Some("ECDSA".into()),
         data: RequestData {
             hashed_files_signing: HashedFilesSigning {
                 hashed_files: vec![HashedFile {
-                    file_name: "agreement.pdf".into(),
+                    file_name: format!("{document}.pdf"),
                     file_hash: base64_hash,
                 }],
             },
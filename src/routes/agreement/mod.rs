@@ -1,8 +1,11 @@
 pub mod demo;
+pub mod find;
 pub mod generate;
 pub mod get;
 pub mod get_sign_link;
 pub mod get_signed;
 pub mod reject;
 pub mod remove;
+pub mod renew;
 pub mod status;
+pub mod terminate;
@@ -1 +1,68 @@
+use axum::extract::{Json, State};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
 
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        auth::{require_party, AuthUser},
+        cache::AgreementProposalKey,
+        db::{apply_transition_on, get_agreement_key, AgreementAction},
+        server_error::ServerError,
+    },
+};
+
+/// The input payload to reject an agreement
+#[derive(Deserialize)]
+pub struct Payload {
+    pub agreement_id: Uuid,
+    /// An optional human-readable reason of the rejection.
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    pub success: bool,
+}
+
+/// Rejects the agreement proposal between tenant and landlord.
+///
+/// Either party can reject the proposal until somebody signs it.
+/// After that, the proposal can't be generated or signed until it's re-initiated.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Json(payload): Json<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement = get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    apply_transition_on(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+        AgreementAction::Reject {
+            by: uid,
+            reason: payload.reason,
+        },
+    )
+    .await?;
+
+    // dropping the pending confirmations of the proposal
+    state
+        .cache
+        .invalidate(&AgreementProposalKey {
+            tenant_id: agreement.tenant_id.to_string(),
+            landlord_id: agreement.landlord_id.to_string(),
+            housing_id: agreement.housing_id.to_string(),
+        })
+        .await;
+
+    info!("Agreement {} was rejected by {uid}", agreement.id);
+
+    Ok(Json(Response { success: true }))
+}
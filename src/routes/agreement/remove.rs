@@ -1,14 +1,7 @@
-use std::sync::Arc;
-
-use anyhow::anyhow;
 use axum::{
     extract::{Json, State},
     response::{IntoResponse, Response},
 };
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::info;
@@ -17,23 +10,17 @@ use uuid::Uuid;
 use crate::{
     commands::server::ServerState,
     utils::{
-        db::{delete_latest_agreement},
+        auth::{require_party, AuthUser},
+        db::{delete_agreement_by_id, get_agreement_key},
         s3::{get_key_for_s3, get_signature_key_for_s3},
         server_error::ServerError,
-        verify_jwt::verify_jwt,
     },
 };
 
 /// The input payload to remove an agreement
 #[derive(Deserialize)]
 pub struct RemoveAgreementPayload {
-    pub tenant_id: Uuid,
-    pub landlord_id: Uuid,
-    pub housing_id: Uuid,
-
-    /// backdoor for testing
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
+    pub agreement_id: Uuid,
 }
 
 /// Response for `remove_agreement` endpoint
@@ -48,63 +35,21 @@ pub struct RemoveAgreementResponse {
 /// we proceed with removal. Otherwise, we fail.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
+    AuthUser(uid): AuthUser,
     Json(payload): Json<RemoveAgreementPayload>,
 ) -> Result<Response, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
+    let agreement = get_agreement_key(&state.db_pool, payload.agreement_id).await?;
 
     // check that the caller is either the tenant or landlord
-    if uid != payload.tenant_id && uid != payload.landlord_id {
-        return Err(anyhow!(
-            "you are not authorized to remove this agreement: you're not a landlord or a tenant"
-        )
-        .into());
-    }
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
 
-    // remove from DB
-    //   - remove the row in `agreements` table
-    //   - remove the row in `signatures` table
-    //     (and return the removed signature if it existed)
-    let _deleted_agreement = delete_latest_agreement(
-        &state.db_pool,
-        payload.tenant_id,
-        payload.landlord_id,
-        payload.housing_id,
-    )
-    .await?;
-
-    let _removed_signatures = remove_signature_entry(
-        &state.db_pool,
-        payload.tenant_id,
-        payload.landlord_id,
-        payload.housing_id,
-    )
-    .await?;
+    // remove from DB: the row in `agreements` table, together with its signatures and amendments
+    let _deleted_agreement = delete_agreement_by_id(&state.db_pool, agreement.id).await?;
 
     // removing from S3
-    let pdf_key = get_key_for_s3(
-        payload.tenant_id.clone(),
-        payload.landlord_id.clone(),
-        payload.housing_id.clone(),
-    );
+    let pdf_key = get_key_for_s3(agreement.id);
 
-    let sig_key = get_signature_key_for_s3(
-        payload.tenant_id.clone(),
-        payload.landlord_id.clone(),
-        payload.housing_id.clone(),
-    );
+    let sig_key = get_signature_key_for_s3(agreement.id);
 
     info!("Removing from S3: {pdf_key} and {sig_key}");
 
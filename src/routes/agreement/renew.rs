@@ -0,0 +1,199 @@
+use anyhow::anyhow;
+use axum::{extract::State, Json};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        agreement::RenewalChanges,
+        agreements::{render_agreement, store_rendered_agreement},
+        auth::{require_party, AuthUser},
+        db::{self, AgreementKey, AgreementState},
+        s3,
+        server_error::ServerError,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct Payload {
+    /// The agreement to renew.
+    pub agreement_id: Uuid,
+    /// The terms that differ from the renewed agreement.
+    #[serde(default)]
+    pub changes: RenewalChanges,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// The id of the new agreement.
+    pub agreement_id: Uuid,
+    /// The date of the new agreement.
+    pub date: NaiveDate,
+    /// The contract number of the new agreement.
+    pub number: i32,
+}
+
+/// Renews the signed or expired agreement, if it's the latest one between the parties.
+///
+/// The new agreement takes the terms of the renewed one, updated by its signed amendments
+/// and by `changes`, and the current documents of the parties. It's rendered right away
+/// with the latest version of the same template, and both parties sign it
+/// through `/agreement/get_sign_link`. If its PDF can't be stored, the renewal stays
+/// generated, and renewing the same agreement again renders it from what's stored with it.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Json(payload): Json<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    let previous = db::get_agreement_state(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        None,
+    )
+    .await?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    // the PDF of the renewal wasn't stored, the renewal is rendered again as is
+    if previous.renewal_of == Some(agreement.id) && previous.state == AgreementState::Generated {
+        let number = previous
+            .number
+            .ok_or_else(|| anyhow!("generated agreement {} has no number", previous.id))?;
+        let key = AgreementKey {
+            id: previous.id,
+            date: previous.date,
+            ..agreement
+        };
+        store_rendered_agreement(&state, &key, number).await?;
+
+        return Ok(Json(Response {
+            agreement_id: previous.id,
+            date: previous.date,
+            number,
+        }));
+    }
+
+    if previous.id != agreement.id {
+        return Err(ServerError::Conflict(
+            "the agreement was already followed by another one".into(),
+        ));
+    }
+
+    match previous.state {
+        AgreementState::Signed | AgreementState::Expired => {}
+        AgreementState::Terminated => {
+            return Err(ServerError::Conflict(
+                "the agreement was terminated, it can't be renewed".into(),
+            ));
+        }
+        _ => {
+            return Err(ServerError::Conflict(
+                "only a signed or expired agreement can be renewed".into(),
+            ));
+        }
+    }
+
+    let mut snapshot = db::get_agreement_snapshot(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+    )
+    .await?
+    .ok_or_else(|| {
+        ServerError::Conflict(
+            "the terms of the agreement are not stored, it can't be renewed".into(),
+        )
+    })?;
+
+    for signed in db::get_signed_amendment_changes(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+    )
+    .await?
+    {
+        signed.apply(&mut snapshot.inputs.rent_data);
+    }
+
+    let mut inputs = snapshot.inputs;
+    payload.changes.apply(&mut inputs.rent_data)?;
+    inputs.tenant =
+        (*db::get_document_unit_from_db(&state.db_pool, &agreement.tenant_id.to_string()).await?)
+            .clone();
+    inputs.landlord =
+        (*db::get_document_unit_from_db(&state.db_pool, &agreement.landlord_id.to_string())
+            .await?)
+            .clone();
+
+    let template = state.templates.latest(snapshot.template_id)?;
+    let stored_inputs = serde_json::to_value(&inputs)?;
+
+    let record = db::create_renewal(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.id,
+        uid,
+    )
+    .await?;
+
+    let key = AgreementKey {
+        id: record.id,
+        tenant_id: agreement.tenant_id,
+        landlord_id: agreement.landlord_id,
+        housing_id: agreement.housing_id,
+        date: record.date,
+    };
+
+    // the renewal can be retried only if the unrendered agreement is gone
+    let rendered = match render_agreement(
+        &state,
+        &key,
+        &template,
+        record.transitions.created_at,
+        &stored_inputs,
+    )
+    .await
+    {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            db::delete_unrendered_agreement(
+                &state.db_pool,
+                agreement.tenant_id,
+                agreement.landlord_id,
+                agreement.housing_id,
+                record.date,
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+    let number = rendered.number;
+
+    // the renewal gets a PDF of its own, the signed one of the renewed agreement stays
+    s3::create_agreement_pdf(&state, rendered.pdf, record.id).await?;
+
+    info!(
+        "Agreement {} was renewed by {uid} as {} No. {number}",
+        agreement.id, record.id
+    );
+
+    Ok(Json(Response {
+        agreement_id: record.id,
+        date: record.date,
+        number,
+    }))
+}
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use axum::{
     extract::{Query, State},
     Json,
@@ -9,7 +10,8 @@ use uuid::Uuid;
 use crate::{
     commands::server::ServerState,
     utils::{
-        db::{get_agreement, get_agreements_for_tenant_and_landlord},
+        auth::{require_party, AuthUser},
+        db::{get_agreement_state, AgreementState, AgreementTransitions},
         server_error::ServerError,
     },
 };
@@ -20,30 +22,67 @@ use crate::{
 pub enum AgreementStatus {
     NotInitiated,
     Initiated { by: Uuid },
-    Rejected {by: Uuid},
+    Rejected { by: Uuid },
     Generated,
     HalfSigned { by: Uuid },
     Signed,
+    Expired,
+    Terminated,
+}
+
+impl AgreementStatus {
+    /// Maps the state of the agreement and the `action_by` column into the status.
+    fn from_db(state: AgreementState, action_by: Option<Uuid>) -> Result<Self, ServerError> {
+        let by = || {
+            action_by.ok_or_else(|| {
+                anyhow!("agreement in state `{}` has no `action_by`", state.as_str())
+            })
+        };
 
+        Ok(match state {
+            AgreementState::NotInitiated => AgreementStatus::NotInitiated,
+            AgreementState::Initiated => AgreementStatus::Initiated { by: by()? },
+            AgreementState::Rejected => AgreementStatus::Rejected { by: by()? },
+            AgreementState::Generated => AgreementStatus::Generated,
+            AgreementState::HalfSigned => AgreementStatus::HalfSigned { by: by()? },
+            AgreementState::Signed => AgreementStatus::Signed,
+            AgreementState::Expired => AgreementStatus::Expired,
+            AgreementState::Terminated => AgreementStatus::Terminated,
+        })
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Payload {
-    tenant_id: String,
-    landlord_id: String,
-    housing_id: String,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
     date: Option<NaiveDate>,
 }
 
 #[derive(Serialize)]
 pub struct Response {
+    /// The id of the agreement the status belongs to.
+    agreement_id: Uuid,
     status: AgreementStatus,
+    /// The date of the agreement the status belongs to.
+    date: NaiveDate,
+    /// The party that performed the latest action, if any.
+    action_by: Option<Uuid>,
+    /// The id of the agreement this one renews, if any.
+    renewal_of: Option<Uuid>,
+    /// The contract number, once the agreement is rendered.
+    number: Option<i32>,
+    /// When each of the transitions happened.
+    transitions: AgreementTransitions,
 }
 
 /// Returns the status of the agreement between tenant and landlord.
 /// The date field is optional: if not passed, the latest agreement will be considered.
+/// Only the tenant and the landlord can see it.
 pub async fn handler(
     State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
     Query(Payload {
         tenant_id,
         landlord_id,
@@ -51,6 +90,19 @@ pub async fn handler(
         date,
     }): Query<Payload>,
 ) -> Result<Json<Response>, ServerError> {
-    // TODO: getting the status from the DB column
-    todo!()
+    require_party(uid, tenant_id, landlord_id)?;
+
+    let record = get_agreement_state(&state.db_pool, tenant_id, landlord_id, housing_id, date)
+        .await?
+        .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    Ok(Json(Response {
+        agreement_id: record.id,
+        status: AgreementStatus::from_db(record.state, record.action_by)?,
+        date: record.date,
+        action_by: record.action_by,
+        renewal_of: record.renewal_of,
+        number: record.number,
+        transitions: record.transitions,
+    }))
 }
@@ -0,0 +1,74 @@
+use axum::{extract::State, Json};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        agreement::{AmendmentChanges, AmendmentKind},
+        amendments::issue_amendment,
+        auth::{require_party, AuthUser},
+        db,
+        notifications::{notify, Notification},
+        server_error::ServerError,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct Payload {
+    pub agreement_id: Uuid,
+    /// The moment the agreement ends instead of its end date.
+    pub end: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// The number of the termination act among the amendments of the agreement.
+    pub number: i32,
+}
+
+/// Proposes to terminate the signed agreement before its end date.
+///
+/// A termination act is rendered right away. Both parties sign it like any other
+/// amendment, through `/agreement/get_sign_link` with its number. Once signed,
+/// the agreement becomes `terminated` at `end`.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Json(payload): Json<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    let key = issue_amendment(
+        &state,
+        uid,
+        &agreement,
+        AmendmentKind::Termination,
+        AmendmentChanges {
+            end: Some(payload.end),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    info!(
+        "Termination of the agreement {} was proposed by {uid}",
+        agreement.id
+    );
+
+    notify(
+        &state.config.notifications,
+        Notification::TerminationProposed {
+            agreement,
+            number: key.number,
+            by: uid,
+            end: payload.end,
+        },
+    );
+
+    Ok(Json(Response { number: key.number }))
+}
@@ -0,0 +1,64 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        agreement::{AmendmentChanges, AmendmentKind},
+        amendments::issue_amendment,
+        auth::{require_party, AuthUser},
+        db,
+        server_error::ServerError,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct Payload {
+    pub agreement_id: Uuid,
+    /// The terms to change, the rest of them stay as agreed.
+    pub changes: AmendmentChanges,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    /// The number of the amendment within the agreement.
+    pub number: i32,
+}
+
+/// Creates an amendment to the signed agreement.
+///
+/// The amendment is rendered right away from the stored terms of the agreement,
+/// and both parties sign it through `/agreement/get_sign_link` with its number.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Json(payload): Json<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    if payload.changes.is_empty() {
+        return Err(ServerError::BadRequest(
+            "the amendment doesn't change anything".into(),
+        ));
+    }
+
+    let key = issue_amendment(
+        &state,
+        uid,
+        &agreement,
+        AmendmentKind::Amendment,
+        payload.changes,
+    )
+    .await?;
+
+    info!(
+        "Amendment {} to the agreement {} was created by {uid}",
+        key.number, key.agreement_id
+    );
+
+    Ok(Json(Response { number: key.number }))
+}
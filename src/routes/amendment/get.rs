@@ -0,0 +1,53 @@
+use anyhow::anyhow;
+use axum::{
+    extract::{Query, State},
+    response::Response,
+};
+use http::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        auth::{require_party, AuthUser},
+        db, s3,
+        server_error::ServerError,
+    },
+};
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct Payload {
+    pub agreement_id: Uuid,
+    /// The number of the amendment.
+    pub number: i32,
+}
+
+/// Returns the PDF of an amendment to the agreement.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Query(payload): Query<Payload>,
+) -> Result<Response, ServerError> {
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    let key = agreement.amendment(payload.number);
+
+    let pdf = s3::get_amendment_pdf(&state, &key).await?;
+
+    let filename = s3::get_amendment_key_for_s3(&key);
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/pdf")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}.pdf\""),
+        )
+        .body(axum::body::Body::from(pdf))
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    Ok(response)
+}
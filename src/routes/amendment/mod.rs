@@ -0,0 +1,4 @@
+pub mod create;
+pub mod get;
+pub mod get_signed;
+pub mod reject;
@@ -0,0 +1,61 @@
+use axum::extract::{Json, State};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    commands::server::ServerState,
+    utils::{
+        auth::{require_party, AuthUser},
+        db::{self, AgreementAction},
+        server_error::ServerError,
+    },
+};
+
+/// The input payload to reject an amendment
+#[derive(Deserialize)]
+pub struct Payload {
+    pub agreement_id: Uuid,
+    /// The number of the amendment.
+    pub number: i32,
+    /// An optional human-readable reason of the rejection.
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Response {
+    pub success: bool,
+}
+
+/// Rejects an amendment to the agreement.
+///
+/// Either party can reject the amendment until somebody signs it.
+/// The agreement stays as it was, and a new amendment can be created.
+pub async fn handler(
+    State(state): State<ServerState>,
+    AuthUser(uid): AuthUser,
+    Json(payload): Json<Payload>,
+) -> Result<Json<Response>, ServerError> {
+    let agreement = db::get_agreement_key(&state.db_pool, payload.agreement_id).await?;
+
+    require_party(uid, agreement.tenant_id, agreement.landlord_id)?;
+
+    let key = agreement.amendment(payload.number);
+
+    db::apply_amendment_transition(
+        &state.db_pool,
+        &key,
+        AgreementAction::Reject {
+            by: uid,
+            reason: payload.reason,
+        },
+    )
+    .await?;
+
+    info!(
+        "Amendment {} to the agreement {} was rejected by {uid}",
+        key.number, key.agreement_id
+    );
+
+    Ok(Json(Response { success: true }))
+}
@@ -2,8 +2,12 @@ use std::str::from_utf8;
 
 use crate::{
     commands::server::ServerState,
-    routes::user::get_sharing_link::DiiaSharingRequestId,
-    utils::{db, eusign::*, server_error::ServerError},
+    utils::{
+        db::{self, DiiaRequest},
+        diia::take_diia_request_by_token,
+        eusign::*,
+        server_error::ServerError,
+    },
 };
 use anyhow::anyhow;
 use axum::extract::{Json, Multipart, State};
@@ -20,7 +24,8 @@ pub struct Response {
 /// For now, the pipeline of handling the data is:
 /// 1. Decrypting the data using EUSignCP library.
 /// 2. Verifying that the data is signed by Diia public certificate.
-/// 3. Storing the data inside the database.
+/// 3. Matching the request id against the request issued by `get_sharing_link`.
+/// 4. Storing the data inside the database.
 pub async fn handler(
     State(state): State<ServerState>,
     mut multipart: Multipart,
@@ -61,9 +66,14 @@ pub async fn handler(
         // Deserializing using serde
         let result: DecryptionResult = serde_json::from_str(&result)?;
 
-        // Getting user_id and random seed
-        let sharing_request_id: DiiaSharingRequestId = serde_json::from_str(&result.request_id)?;
-        let uid = sharing_request_id.uid.replace("\"", "");
+        // Taking the request, every one is answered once
+        let DiiaRequest::Sharing { user_id: uid } =
+            take_diia_request_by_token(&state.db_pool, &result.request_id).await?
+        else {
+            return Err(ServerError::Unauthorized(
+                "the request id is not of a sharing request".into(),
+            ));
+        };
 
         // Getting the actual passport data
         let data = result.data;
@@ -86,7 +96,7 @@ pub async fn handler(
         };
 
         // Store in database
-        db::store_document_unit(&state.db_pool, &uid, &unit).await?;
+        db::store_document_unit(&state.db_pool, &uid.to_string(), &unit).await?;
 
         info!("Added user with id={uid} to the database!");
     }
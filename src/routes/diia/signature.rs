@@ -2,9 +2,10 @@ use std::str::from_utf8;
 
 use crate::{
     commands::server::ServerState,
-    routes::agreement::get_sign_link::SignHashRequestId,
     utils::{
-        db,
+        db::{self, AgreementState, DiiaRequest},
+        diia::{diia_token_hash, find_diia_request_by_token},
+        eusign::verify_signature,
         server_error::ServerError,
     },
 };
@@ -36,16 +37,45 @@ pub struct SignedHash {
 /// This route handles signed hashes of the agreement that come from Diia Signature.
 ///
 /// For now, the pipeline of handling the data is:
-/// 1. Getting the signature from the request
-/// 2. Adding signature to signatures DB
-/// 3. Updating the cache.
+/// 1. Matching the request id against the request issued by `get_sign_link`.
+/// 2. Getting the signature from the request.
+/// 3. Verifying the signature: that it's over the PDF sent to be signed,
+///    that the certificate is good by OCSP and issued to the signing party.
+/// 4. Adding signature and its verification report to signatures DB.
+///
+/// The request is used up only in step 4, so a callback that fails before that
+/// can be repeated.
 pub async fn handler(
     State(state): State<ServerState>,
     headers: HeaderMap,
     mut multipart: Multipart,
 ) -> Result<Json<Response>, ServerError> {
-    // 1. Decoding the message
-    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+    // 1. Looking the request up, every one is answered once
+    let token = headers
+        .get("X-Document-Request-Trace-Id")
+        .ok_or_else(|| ServerError::Unauthorized("the request id is missing".into()))?
+        .to_str()
+        .map_err(|_| ServerError::Unauthorized("the request id is invalid".into()))?;
+
+    let DiiaRequest::Signature {
+        signed_by,
+        agreement_id,
+        amendment,
+        file_hash,
+    } = find_diia_request_by_token(&state.db_pool, token).await?
+    else {
+        return Err(ServerError::Unauthorized(
+            "the request id is not of a signature request".into(),
+        ));
+    };
+
+    // 2. Decoding the message
+    let mut encoded = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ServerError::BadRequest(format!("cannot read the request: {e}")))?
+    {
         let name = field.name().unwrap_or("<unnamed>").to_string();
 
         let file_name = field
@@ -53,7 +83,10 @@ pub async fn handler(
             .map(|s| s.to_string())
             .unwrap_or_else(|| name.to_string());
         let content_type = field.content_type().map(|s| s.to_string());
-        let value = field.bytes().await.unwrap_or_else(|_| vec![].into());
+        let value = field
+            .bytes()
+            .await
+            .map_err(|e| ServerError::BadRequest(format!("cannot read `{name}`: {e}")))?;
 
         info!("Field Name: {}", name);
         info!("File Name: {}", file_name);
@@ -65,47 +98,71 @@ pub async fn handler(
             &value[..std::cmp::min(value.len(), 50)]
         );
 
-        if name != "encodeData" {
-            continue;
+        if name == "encodeData" {
+            encoded = Some(value);
+            break;
         }
+    }
+    let value = encoded.ok_or_else(|| ServerError::BadRequest("`encodeData` is missing".into()))?;
 
-        let result = BASE64_STANDARD.decode(value)?;
-        let result = from_utf8(&result)?;
-        let mut result: SignedHash = serde_json::from_str(result)?;
+    let result = BASE64_STANDARD.decode(value)?;
+    let result = from_utf8(&result)?;
+    let mut result: SignedHash = serde_json::from_str(result)?;
 
-        let SignHashRequestId {
-            tenant_id,
-            landlord_id,
-            signed_by,
-            housing_id,
-            ..
-        } = serde_json::from_str(
-            headers
-                .get("X-Document-Request-Trace-Id")
-                .ok_or(anyhow!("wasn't able to get sign hash request id header"))?
-                .to_str()?,
-        )?;
-
-        let signature = result
-            .signed_items
-            .pop()
-            .context("cannot extract signature")?
-            .signature;
-
-        // 2. Updating signatures DB
-        db::persist_signature(
+    let signature = result
+        .signed_items
+        .pop()
+        .context("cannot extract signature")?
+        .signature;
+
+    // 3. Checking that the very PDF sent to be signed was signed by the very party
+    let party = db::get_document_unit_from_db(&state.db_pool, &signed_by.to_string()).await?;
+    let report = verify_signature(&file_hash, &signature, &party)?;
+
+    let agreement = db::get_agreement_key(&state.db_pool, agreement_id).await?;
+    let token_hash = diia_token_hash(token);
+
+    // an amendment is signed under the agreement, its signatures are combined the same way
+    if let Some(number) = amendment {
+        let key = agreement.amendment(number);
+
+        if let Some(entry) = db::persist_amendment_signature(
             &state.db_pool,
-            tenant_id,
-            landlord_id,
-            housing_id,
+            &key,
+            &token_hash,
             signed_by,
-            signature
+            signature,
+            &report,
         )
-        .await?;
+        .await?
+        {
+            state
+                .amendment_signature_sender
+                .send(entry)
+                .map_err(|e| anyhow!("cannot schedule the amendment signing: {e}"))?;
+        }
+
+        return Ok(Json(Response { success: true }));
+    }
+
+    // 4. Updating signatures DB, the request is taken along
+    let agreement_state = db::persist_signature(
+        &state.db_pool,
+        &agreement,
+        &token_hash,
+        signed_by,
+        signature,
+        &report,
+    )
+    .await?;
 
-        // 3. Updating the DB (changing tenant_signed or landlord_singed)
-        // TODO
-        
+    // once both parties signed, the signatures are combined into a single file
+    if agreement_state == AgreementState::Signed {
+        let entry = db::get_signature_entry(&state.db_pool, &agreement).await?;
+        state
+            .signature_sender
+            .send(entry)
+            .map_err(|e| anyhow!("cannot schedule the agreement signing: {e}"))?;
     }
 
     Ok(Json(Response { success: true }))
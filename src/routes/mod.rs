@@ -6,3 +6,9 @@ pub mod diia;
 
 /// Routes that handle agreement creation and signing.
 pub mod agreement;
+
+/// Routes for the maintainers of the service.
+pub mod admin;
+
+/// Routes that handle amendments to signed agreements.
+pub mod amendment;
@@ -1,6 +1,6 @@
 use crate::{
     commands::server::ServerState,
-    utils::{server_error::ServerError, verify_jwt::verify_jwt},
+    utils::{auth::AuthUser, db::DiiaRequest, diia::issue_diia_request, server_error::ServerError},
 };
 use anyhow::anyhow;
 use axum::{
@@ -8,39 +8,18 @@ use axum::{
     Json,
 };
 
-#[cfg(feature = "dev")]
-use axum::extract::Query;
-
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
 use http::{
     header::{ACCEPT, AUTHORIZATION},
     HeaderMap, HeaderValue,
 };
 use serde::{Deserialize, Serialize};
 use tracing::error;
-use uuid::Uuid;
-
-#[derive(Deserialize)]
-pub struct Payload {
-    /// This is a backdoor for testing purposes
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
-}
 
 #[derive(Serialize)]
 pub struct Response {
     deeplink: String,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct DiiaSharingRequestId {
-    pub uid: Uuid,
-    pub seed: Uuid,
-}
-
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiiaSharingRequest {
@@ -57,39 +36,17 @@ pub struct DiiaSharingResponse {
 /// Generates an authorization link for Diia sharing.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
-    #[cfg(feature = "dev")] Query(payload): Query<Payload>,
+    AuthUser(uid): AuthUser,
 ) -> Result<Json<Response>, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    let request_id = DiiaSharingRequestId {
-        uid,
-        seed: Uuid::new_v4(),
-    };
-
-    // Serialize request_id to string and handle errors properly
-    let request_id_str = match serde_json::to_string(&request_id) {
-        Ok(str) => str,
-        Err(e) => return Err(anyhow!("Failed to serialize request ID: {}", e).into()),
-    };
+    // the callback with the documents is matched against the request
+    let request_id =
+        issue_diia_request(&state.db_pool, &DiiaRequest::Sharing { user_id: uid }).await?;
 
     // setting up the request
     let request = DiiaSharingRequest {
         offer_id: state.config.diia.offer_sharing_id.clone(),
         return_link: "https://mykaze.org".into(),
-        request_id: request_id_str,
+        request_id,
     };
 
     // setting up endpoint
@@ -1,27 +1,13 @@
 use crate::{
     commands::server::ServerState,
-    utils::{db, server_error::ServerError, verify_jwt::verify_jwt},
+    utils::{auth::AuthUser, db, server_error::ServerError},
 };
 use axum::{
     extract::State,
     Json,
 };
 
-#[cfg(feature = "dev")]
-use axum::extract::Query;
-
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
-use serde::{Deserialize, Serialize};
-
-#[derive(Deserialize)]
-pub struct Payload {
-    /// This is a backdoor for testing purposes
-    #[cfg(feature = "dev")]
-    pub _uid: Option<Uuid>,
-}
+use serde::Serialize;
 
 #[derive(Serialize)]
 pub struct Response {
@@ -31,24 +17,9 @@ pub struct Response {
 /// Generates an authorization link for Diia sharing.
 pub async fn handler(
     State(state): State<ServerState>,
-    TypedHeader(Authorization(bearer)): TypedHeader<Authorization<Bearer>>,
-    #[cfg(feature = "dev")] Query(payload): Query<Payload>,
+    AuthUser(uid): AuthUser,
 ) -> Result<Json<Response>, ServerError> {
-    #[cfg(feature = "dev")]
-    let uid = if let Some(_uid) = payload._uid {
-        _uid
-    } else {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    #[cfg(feature = "default")]
-    let uid = {
-        let token = bearer.token();
-        verify_jwt(token, &state).await?
-    };
-
-    if (db::delete_document_unit(&state.db_pool, uid).await).is_ok() {
+    if (db::delete_document_unit(&state.db_pool, &uid.to_string()).await).is_ok() {
         Ok(Json(Response { success: true }))
     } else {
         Ok(Json(Response { success: false }))
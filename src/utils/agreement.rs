@@ -1,439 +1,19 @@
-use super::{eusign::DocumentUnit, server_error::ServerError};
-use crate::commands::server::ServerState;
+use super::{eusign::DocumentUnit, server_error::ServerError, templates::TemplateId};
+use anyhow::anyhow;
 use chrono::{DateTime, Datelike, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::{Europe::Kyiv, Tz};
-use regex::Regex;
+use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::{collections::BTreeMap, str::FromStr};
 
-/////////////////////////////////////
-//   1) Custom Serde Error         //
-/////////////////////////////////////
-
-#[derive(Debug)]
-pub enum TypstSerError {
-    Message(String),
-}
-
-impl std::fmt::Display for TypstSerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            TypstSerError::Message(msg) => write!(f, "{msg}"),
-        }
-    }
-}
-
-impl std::error::Error for TypstSerError {}
-
-impl serde::ser::Error for TypstSerError {
-    fn custom<T: std::fmt::Display>(msg: T) -> Self {
-        TypstSerError::Message(msg.to_string())
-    }
-}
-
-/////////////////////////////////////
-//  2) Custom Typst Serializer     //
-/////////////////////////////////////
-
-/// Convert a `T` (which implements `Serialize`) into a Typst‐style string.
-/// **Then** do a small post‐processing step to remove quotes around `datetime(...)`.
-pub fn to_typst_string<T>(value: &T) -> Result<String, TypstSerError>
-where
-    T: Serialize,
-{
-    let mut serializer = TypstSerializer::new();
-    value.serialize(&mut serializer)?;
-    let mut result = serializer.output;
-
-    // Post‐processing: remove quotes around any "datetime(...)" strings using regex
-    let re = Regex::new(r#""datetime\((.*?)\)""#).unwrap();
-    result = re.replace_all(&result, "datetime($1)").to_string();
-
-    Ok(result)
-}
-
-/// Internal struct that accumulates the output during serialization.
-struct TypstSerializer {
-    pub output: String,
-    pub _level: usize,
-}
-
-impl TypstSerializer {
-    fn new() -> Self {
-        Self {
-            output: String::new(),
-            _level: 0,
-        }
-    }
-    fn _indent(&mut self) {
-        for _ in 0..self._level {
-            self.output.push_str("  ");
-        }
-    }
-}
-
-use serde::ser::{
-    SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
-    SerializeTupleStruct, SerializeTupleVariant, Serializer,
-};
-
-////////////////////////////////////////
-//  3) Implement `Serializer`         //
-////////////////////////////////////////
-
-impl<'a> Serializer for &'a mut TypstSerializer {
-    // Required associated types:
-    type Ok = ();
-    type Error = TypstSerError;
-
-    type SerializeSeq = Compound<'a>;
-    type SerializeTuple = Compound<'a>;
-    type SerializeTupleStruct = Compound<'a>;
-    type SerializeTupleVariant = VariantCompound<'a>;
-    type SerializeMap = Compound<'a>;
-    type SerializeStruct = StructCompound<'a>;
-    type SerializeStructVariant = StructVariantCompound<'a>;
-
-    // ---- Primitives ----
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        self.output += if v { "true" } else { "false" };
-        Ok(())
-    }
-
-    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        self.output += &v.to_string();
-        Ok(())
-    }
-    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-        self.output.push('"');
-        self.output.push(v);
-        self.output.push('"');
-        Ok(())
-    }
-    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        // Always quote strings in Typst:
-        self.output.push('"');
-        self.output += v;
-        self.output.push('"');
-        Ok(())
-    }
-    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        Err(TypstSerError::Message(
-            "serialize_bytes not supported".to_string(),
-        ))
-    }
-
-    // ---- Option ----
-    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        self.output += "null";
-        Ok(())
-    }
-    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
-        value.serialize(self)
-    }
-    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        // Usually "null" is how we represent a unit
-        self.output += "null";
-        Ok(())
-    }
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        // For unit structs, also "null"
-        self.output += "null";
-        Ok(())
-    }
-    fn serialize_unit_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-    ) -> Result<Self::Ok, Self::Error> {
-        self.output.push_str(variant);
-        Ok(())
-    }
-    fn serialize_newtype_struct<T: ?Sized + Serialize>(
-        self,
-        _name: &'static str,
-        value: &T,
-    ) -> Result<Self::Ok, Self::Error> {
-        value.serialize(self)
-    }
-    fn serialize_newtype_variant<T: ?Sized + Serialize>(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-        value: &T,
-    ) -> Result<Self::Ok, Self::Error> {
-        self.output.push_str(variant);
-        self.output.push_str(": ");
-        value.serialize(self)
-    }
-
-    // ---- Seq/Tuple/Map/Struct ----
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        self.output.push('(');
-        Ok(Compound {
-            ser: self,
-            first: true,
-            _is_map: false,
-        })
-    }
-    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        self.serialize_seq(Some(len))
-    }
-    fn serialize_tuple_struct(
-        self,
-        _name: &'static str,
-        len: usize,
-    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        self.serialize_seq(Some(len))
-    }
-    fn serialize_tuple_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-        _len: usize,
-    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        self.output.push_str(variant);
-        self.output.push_str(": (");
-        Ok(VariantCompound {
-            ser: self,
-            first: true,
-        })
-    }
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        self.output.push('(');
-        Ok(Compound {
-            ser: self,
-            first: true,
-            _is_map: true,
-        })
-    }
-    fn serialize_struct(
-        self,
-        _name: &'static str,
-        _len: usize,
-    ) -> Result<Self::SerializeStruct, Self::Error> {
-        self.output.push('(');
-        Ok(StructCompound {
-            ser: self,
-            first: true,
-        })
-    }
-    fn serialize_struct_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-        _len: usize,
-    ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        self.output.push_str(variant);
-        self.output.push_str(": (");
-        Ok(StructVariantCompound {
-            ser: self,
-            first: true,
-        })
-    }
-}
-
-////////////////////////////////////
-// 4) Seq/Tuple/Map/Struct Helpers
-////////////////////////////////////
-struct Compound<'a> {
-    ser: &'a mut TypstSerializer,
-    first: bool,
-    _is_map: bool,
-}
-
-impl SerializeSeq for Compound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TypstSerError> {
-        value.serialize(&mut *self.ser)?;
-        self.ser.output.push_str(", ");
-        Ok(())
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        self.ser.output.push(')');
-        Ok(())
-    }
-}
-
-impl SerializeTuple for Compound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TypstSerError> {
-        SerializeSeq::serialize_element(self, value)
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        SerializeSeq::end(self)
-    }
-}
-
-impl SerializeTupleStruct for Compound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TypstSerError> {
-        SerializeSeq::serialize_element(self, value)
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        SerializeSeq::end(self)
-    }
-}
-
-impl SerializeMap for Compound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), TypstSerError> {
-        if !self.first {
-            self.ser.output.push_str(", ");
-        }
-        self.first = false;
-
-        // Key is presumably a string -> we do NOT strip quotes now.
-        // Instead, we keep them so that we get e.g. ("some key": 123).
-        let mut key_buf = TypstSerializer::new();
-        key.serialize(&mut key_buf)?;
-        // e.g. key_buf.output might be `"Кондиціонер (AERO 2020)"`
-        // We want that exactly.
-        self.ser.output.push_str(&key_buf.output);
-        self.ser.output.push_str(": ");
-        Ok(())
-    }
-
-    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TypstSerError> {
-        value.serialize(&mut *self.ser)?;
-        Ok(())
-    }
-
-    fn end(self) -> Result<(), TypstSerError> {
-        self.ser.output.push(')');
-        Ok(())
-    }
-}
-
-struct VariantCompound<'a> {
-    ser: &'a mut TypstSerializer,
-    first: bool,
-}
-impl SerializeTupleVariant for VariantCompound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TypstSerError> {
-        if !self.first {
-            self.ser.output.push_str(", ");
-        }
-        self.first = false;
-        value.serialize(&mut *self.ser)?;
-        Ok(())
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        self.ser.output.push(')');
-        Ok(())
-    }
-}
-
-struct StructCompound<'a> {
-    ser: &'a mut TypstSerializer,
-    first: bool,
-}
-impl SerializeStruct for StructCompound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_field<T: ?Sized + Serialize>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), TypstSerError> {
-        if !self.first {
-            self.ser.output.push_str(", ");
-        }
-        self.first = false;
-        self.ser.output.push_str(key);
-        self.ser.output.push_str(": ");
-        value.serialize(&mut *self.ser)?;
-        Ok(())
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        self.ser.output.push(')');
-        Ok(())
-    }
-}
-
-struct StructVariantCompound<'a> {
-    ser: &'a mut TypstSerializer,
-    first: bool,
-}
-impl SerializeStructVariant for StructVariantCompound<'_> {
-    type Ok = ();
-    type Error = TypstSerError;
-
-    fn serialize_field<T: ?Sized + Serialize>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), TypstSerError> {
-        if !self.first {
-            self.ser.output.push_str(", ");
-        }
-        self.first = false;
-        self.ser.output.push_str(key);
-        self.ser.output.push_str(": ");
-        value.serialize(&mut *self.ser)?;
-        Ok(())
-    }
-    fn end(self) -> Result<(), TypstSerError> {
-        self.ser.output.push(')');
-        Ok(())
-    }
-}
+/// The path under which the agreement data is exposed to the Typst template.
+///
+/// The template reads it with `json("/data.json")`, so no user data
+/// ever becomes a part of the Typst source code.
+pub const AGREEMENT_DATA_PATH: &str = "/data.json";
 
 //////////////////////////////////////////////
-// 5) Special handling for DateTime<Tz>    //
+// 1) Special handling for DateTime<Tz>    //
 //////////////////////////////////////////////
 
 #[derive(Debug, Clone)]
@@ -444,23 +24,22 @@ impl Serialize for TypstDateTime {
     where
         S: serde::Serializer,
     {
-        let day = self.0.day();
-        let month = self.0.month();
-        let year = self.0.year();
-        // We'll store as "datetime(day: X, month: Y, year: Z)" in quotes,
-        // then do a .replace(...) to remove them.
-        let s = format!("datetime(day: {day}, month: {month}, year: {year})");
-        serializer.serialize_str(&s)
+        // The template turns `(day: X, month: Y, year: Z)` back into a `datetime`.
+        let mut st = serializer.serialize_struct("TypstDateTime", 3)?;
+        st.serialize_field("day", &self.0.day())?;
+        st.serialize_field("month", &self.0.month())?;
+        st.serialize_field("year", &self.0.year())?;
+        st.end()
     }
 }
 
 //////////////////////////////////////////////
-// 6) Data Structures in CamelCase + `type` //
+// 2) Data Structures in CamelCase + `type` //
 //////////////////////////////////////////////
 
 #[derive(Serialize)]
 pub struct RentalAgreementTitle {
-    pub rental_agreement_number: u64,
+    pub rental_agreement_number: i32,
 }
 
 #[derive(Serialize)]
@@ -569,20 +148,6 @@ pub struct AgreementConditions {
     pub agreement_conditions_data: AgreementConditionsData,
 }
 
-/// Manually implement so #responsibility() not #responsibilitynull
-pub struct Responsibility;
-
-impl Serialize for Responsibility {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        // Empty struct => ( )
-        let st = serializer.serialize_struct("Responsibility", 0)?;
-        st.end()
-    }
-}
-
 #[derive(Serialize)]
 pub struct OtherConditionsData {
     pub min_notice_days_for_visit: u8,
@@ -613,7 +178,7 @@ pub struct AppendixOneData {
     pub place: String,
     pub tenant_initials: String,
     pub landlord_initials: String,
-    pub additional_property: HashMap<String, AdditionalPropertyValue>,
+    pub additional_property: BTreeMap<String, AdditionalPropertyValue>,
     pub meter_readings: MeterReadings,
 }
 
@@ -635,136 +200,69 @@ pub struct AppendixTwo {
     pub appendix_two_data: AppendixTwoData,
 }
 
-////////////////////////////////////////////////////////////
-// 7) The FunctionCall trait to produce #function_name(...)
-////////////////////////////////////////////////////////////
-
-pub trait FunctionCall {
-    fn function_name(&self) -> &'static str;
-    fn to_typst(&self) -> Result<String, TypstSerError>;
-}
-
-// Each "function" struct implements FunctionCall.
-
-impl FunctionCall for RentalAgreementTitle {
-    fn function_name(&self) -> &'static str {
-        "rental_agreement_title"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?; // e.g. (rental_agreement_number: 42)
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for RentalAgreementPlaceAndDate {
-    fn function_name(&self) -> &'static str {
-        "rental_agreement_place_and_date"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for SidesOfAgreement {
-    fn function_name(&self) -> &'static str {
-        "sides_of_agreement"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for SubjectOfAgreement {
-    fn function_name(&self) -> &'static str {
-        "subject_of_agreement"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for RightsAndObligations {
-    fn function_name(&self) -> &'static str {
-        "rights_and_obligations"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for RentalPayment {
-    fn function_name(&self) -> &'static str {
-        "rental_payment"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for AgreementConditions {
-    fn function_name(&self) -> &'static str {
-        "agreement_conditions"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-// For `Responsibility`, we know it's effectively empty:
-impl FunctionCall for Responsibility {
-    fn function_name(&self) -> &'static str {
-        "responsibility"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        // We want "#responsibility()"
-        Ok(format!("#{}\n", self.function_name()))
-    }
-}
-
-impl FunctionCall for OtherConditions {
-    fn function_name(&self) -> &'static str {
-        "other_conditions"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
-
-impl FunctionCall for Signatures {
-    fn function_name(&self) -> &'static str {
-        "signatures"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
-}
+/// The version of [`AgreementInputs`], stored along with them.
+///
+/// Bump it on every change of the inputs that breaks reading the stored ones.
+pub const AGREEMENT_INPUTS_VERSION: i32 = 1;
 
-impl FunctionCall for AppendixOne {
-    fn function_name(&self) -> &'static str {
-        "appendix_one"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
+/// Everything an agreement is rendered from, except for the time, see [`DocumentInfo`].
+///
+/// The inputs are stored with the agreement, so that it can be re-rendered
+/// and its terms can be read without the PDF.
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct AgreementInputs {
+    pub tenant: DocumentUnit,
+    pub landlord: DocumentUnit,
+    /// The housing data, like address or area.
+    pub housing_data: HousingData,
+    /// The rental data, like meter readings or monthly price.
+    pub rent_data: RentData,
+    /// The requisites data, like phone numbers and emails.
+    pub requisites_data: RequisitesData,
+    /// The ownership data, like ownership record number and date.
+    pub ownership_data: OwneshipData,
+}
+
+/// What identifies the rendered document.
+pub struct DocumentInfo {
+    pub agreement_id: String,
+    pub template_id: TemplateId,
+    pub template_version: i32,
+    /// The moment the agreement was created, persisted with it.
+    ///
+    /// This is the only source of time while rendering, so that the agreement
+    /// is rendered into the same bytes every time.
+    pub created_at: DateTime<Utc>,
+}
+
+/// The metadata of the PDF, set by the template with `#set document(..)`.
+#[derive(Serialize)]
+pub struct DocumentMetadata {
+    /// The parties of the agreement.
+    pub author: Vec<String>,
+    /// The agreement id and the template version.
+    pub keywords: Vec<String>,
 }
 
-impl FunctionCall for AppendixTwo {
-    fn function_name(&self) -> &'static str {
-        "appendix_two"
-    }
-    fn to_typst(&self) -> Result<String, TypstSerError> {
-        let body = to_typst_string(self)?;
-        Ok(format!("#{}{}\n", self.function_name(), body))
-    }
+/// Everything the agreement template needs, exposed to it as [`AGREEMENT_DATA_PATH`].
+///
+/// Every field holds the arguments of the template function with the same name,
+/// except for `document`, which is the metadata of the PDF.
+#[derive(Serialize)]
+pub struct AgreementData {
+    pub document: DocumentMetadata,
+    pub rental_agreement_title: RentalAgreementTitle,
+    pub rental_agreement_place_and_date: RentalAgreementPlaceAndDate,
+    pub sides_of_agreement: SidesOfAgreement,
+    pub subject_of_agreement: SubjectOfAgreement,
+    pub rights_and_obligations: RightsAndObligations,
+    pub rental_payment: RentalPayment,
+    pub agreement_conditions: AgreementConditions,
+    pub other_conditions: OtherConditions,
+    pub signatures: Signatures,
+    pub appendix_one: AppendixOne,
+    pub appendix_two: AppendixTwo,
 }
 
 #[derive(Deserialize, Serialize, Default)]
@@ -848,23 +346,25 @@ impl Default for OwneshipData {
 }
 
 ////////////////////////////////////////////////////////////////
-// 8) Example usage: build & return final Typst calls string  //
+// 3) Build the data the agreement template is rendered from  //
 ////////////////////////////////////////////////////////////////
 
-pub async fn generate(
-    state: &ServerState,
-    tenant_data: Arc<DocumentUnit>,
-    landlord_data: Arc<DocumentUnit>,
-    housing_data: HousingData,
-    mut rent_data: RentData,
-    requisites_data: RequisitesData,
-    ownership_data: OwneshipData,
-) -> Result<String, ServerError> {
-    let tenant_passport = tenant_data.internal_passport.clone();
-    let landlord_passport = landlord_data.internal_passport.clone();
-
-    let now: DateTime<Utc> = Utc::now();
-    let now = now.with_timezone(&Kyiv);
+pub fn generate(
+    AgreementInputs {
+        tenant,
+        landlord,
+        housing_data,
+        mut rent_data,
+        requisites_data,
+        ownership_data,
+    }: AgreementInputs,
+    number: i32,
+    info: &DocumentInfo,
+) -> Result<AgreementData, ServerError> {
+    let tenant_passport = tenant.internal_passport;
+    let landlord_passport = landlord.internal_passport;
+
+    let now = info.created_at.with_timezone(&Kyiv);
 
     let ownership_record_date = Kyiv.from_utc_datetime(&ownership_data.date);
     let ownership_record_number = ownership_data.record_number;
@@ -877,7 +377,7 @@ pub async fn generate(
 
     // 1) RentalAgreementTitle
     let fun_title = RentalAgreementTitle {
-        rental_agreement_number: 1,
+        rental_agreement_number: number,
     };
 
     // 2) RentalAgreementPlaceAndDate
@@ -963,10 +463,7 @@ pub async fn generate(
         },
     };
 
-    // 8) Responsibility
-    let fun_responsibility = Responsibility;
-
-    // 9) OtherConditions
+    // 8) OtherConditions
     let mut all_tenants = vec![tenant_initials.clone()];
     all_tenants.append(&mut rent_data.additional_tenants);
     let fun_other_conditions = OtherConditions {
@@ -977,7 +474,7 @@ pub async fn generate(
         },
     };
 
-    // 10) Signatures
+    // 9) Signatures
     let fun_signatures = Signatures {
         tenant: PersonData {
             initials: tenant_initials.clone(),
@@ -1003,7 +500,7 @@ pub async fn generate(
         },
     };
 
-    // 11) AppendixOne
+    // 10) AppendixOne
     let additional_property = rent_data
         .additional_property
         .into_iter()
@@ -1016,7 +513,7 @@ pub async fn generate(
                 },
             )
         })
-        .collect::<HashMap<_, _>>();
+        .collect::<BTreeMap<_, _>>();
 
     let fun_appendix_one = AppendixOne {
         appendix_one_data: AppendixOneData {
@@ -1029,7 +526,7 @@ pub async fn generate(
         },
     };
 
-    // 12) AppendixTwo
+    // 11) AppendixTwo
     let fun_appendix_two = AppendixTwo {
         appendix_two_data: AppendixTwoData {
             starting_date: TypstDateTime(now),
@@ -1039,26 +536,279 @@ pub async fn generate(
         },
     };
 
-    // Generate each function call
-    let calls = vec![
-        fun_title.to_typst()?,
-        fun_place_and_date.to_typst()?,
-        fun_sides.to_typst()?,
-        fun_subject.to_typst()?,
-        fun_rights_and_obligations.to_typst()?,
-        fun_rental_payment.to_typst()?,
-        fun_agreement_conditions.to_typst()?,
-        fun_responsibility.to_typst()?,
-        fun_other_conditions.to_typst()?,
-        fun_signatures.to_typst()?,
-        fun_appendix_one.to_typst()?,
-        fun_appendix_two.to_typst()?,
-    ];
-
-    // Combine them into one big string
-    let all_calls = calls.join("\n");
-
-    let typst_code = (*state.agreement_template_string).clone() + &all_calls;
-
-    Ok(typst_code)
+    let document = DocumentMetadata {
+        author: vec![tenant_initials, landlord_initials],
+        keywords: vec![
+            format!("agreement:{}", info.agreement_id),
+            format!("template:{}/v{}", info.template_id, info.template_version),
+        ],
+    };
+
+    Ok(AgreementData {
+        document,
+        rental_agreement_title: fun_title,
+        rental_agreement_place_and_date: fun_place_and_date,
+        sides_of_agreement: fun_sides,
+        subject_of_agreement: fun_subject,
+        rights_and_obligations: fun_rights_and_obligations,
+        rental_payment: fun_rental_payment,
+        agreement_conditions: fun_agreement_conditions,
+        other_conditions: fun_other_conditions,
+        signatures: fun_signatures,
+        appendix_one: fun_appendix_one,
+        appendix_two: fun_appendix_two,
+    })
+}
+
+///////////////////////////////////////////////////////////////
+// 4) Amendments (addenda) to an already signed agreement    //
+///////////////////////////////////////////////////////////////
+
+/// What an amendment does to the agreement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmendmentKind {
+    /// Changes some of the terms.
+    #[default]
+    Amendment,
+    /// Ends the agreement before its end date.
+    Termination,
+}
+
+impl AmendmentKind {
+    /// The name of the kind, as stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AmendmentKind::Amendment => "amendment",
+            AmendmentKind::Termination => "termination",
+        }
+    }
+
+    /// The template the amendment of this kind is rendered with.
+    pub fn template_id(self) -> TemplateId {
+        match self {
+            AmendmentKind::Amendment => TemplateId::Amendment,
+            AmendmentKind::Termination => TemplateId::Termination,
+        }
+    }
+}
+
+impl FromStr for AmendmentKind {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "amendment" => Ok(AmendmentKind::Amendment),
+            "termination" => Ok(AmendmentKind::Termination),
+            _ => Err(anyhow!("unknown amendment kind `{s}`").into()),
+        }
+    }
+}
+
+/// The terms an amendment changes. The terms left as `None` stay as agreed.
+#[derive(Deserialize, Serialize, Default, Clone)]
+#[serde(default)]
+pub struct AmendmentChanges {
+    /// The new monthly price, in the currency of the agreement.
+    pub price: Option<u64>,
+    /// The new end of the agreement.
+    pub end: Option<NaiveDateTime>,
+    /// The new list of the tenants living with the main one.
+    pub additional_tenants: Option<Vec<String>>,
+    /// The new list of the allowed animals.
+    pub allowed_animals: Option<Vec<String>>,
+}
+
+impl AmendmentChanges {
+    /// Whether the amendment changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.price.is_none()
+            && self.end.is_none()
+            && self.additional_tenants.is_none()
+            && self.allowed_animals.is_none()
+    }
+
+    /// Applies the changes on top of the terms of the agreement.
+    pub fn apply(&self, rent_data: &mut RentData) {
+        if let Some(price) = self.price {
+            rent_data.price = price;
+        }
+        if let Some(end) = self.end {
+            rent_data.end = end;
+        }
+        if let Some(additional_tenants) = &self.additional_tenants {
+            rent_data.additional_tenants = additional_tenants.clone();
+        }
+        if let Some(allowed_animals) = &self.allowed_animals {
+            rent_data.allowed_animals = allowed_animals.clone();
+        }
+    }
+}
+
+/// The terms a renewal changes. The rest are carried over from the renewed agreement.
+#[derive(Deserialize, Serialize, Default, Clone)]
+#[serde(default)]
+pub struct RenewalChanges {
+    /// The start of the new agreement, the end of the renewed one by default.
+    pub start: Option<NaiveDateTime>,
+    /// The changed terms. The end defaults to the start plus the duration of the renewed agreement.
+    #[serde(flatten)]
+    pub terms: AmendmentChanges,
+}
+
+impl RenewalChanges {
+    /// Turns the terms of the renewed agreement into the terms of the new one.
+    pub fn apply(&self, rent_data: &mut RentData) -> Result<(), ServerError> {
+        let start = self.start.unwrap_or(rent_data.end);
+        let end = self
+            .terms
+            .end
+            .unwrap_or(start + (rent_data.end - rent_data.start));
+        if end <= start {
+            return Err(ServerError::BadRequest(
+                "the agreement must end after it starts".into(),
+            ));
+        }
+
+        self.terms.apply(rent_data);
+        rent_data.start = start;
+        rent_data.end = end;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct AmendmentPrice {
+    pub amount: u64,
+    pub currency: String,
+}
+
+/// The changed terms, as the amendment template shows them.
+#[derive(Serialize)]
+pub struct AmendmentTerms {
+    pub price: Option<AmendmentPrice>,
+    pub ending_date: Option<TypstDateTime>,
+    pub additional_tenants: Option<Vec<String>>,
+    pub allowed_animals: Option<Vec<String>>,
+}
+
+/// Everything the amendment template needs, exposed to it as [`AGREEMENT_DATA_PATH`].
+#[derive(Serialize)]
+pub struct AmendmentData {
+    pub document: DocumentMetadata,
+    /// The number of the amendment within the agreement, starting from 1.
+    pub number: i32,
+    /// The date the amended agreement was concluded.
+    pub agreement_date: TypstDateTime,
+    pub place: String,
+    pub date: TypstDateTime,
+    pub tenant_initials: String,
+    pub landlord_initials: String,
+    pub changes: AmendmentTerms,
+}
+
+/// The full name of a party, as written in the documents.
+fn initials(unit: &DocumentUnit) -> String {
+    let passport = &unit.internal_passport;
+    passport.last_name_ua.clone() + " " + &passport.first_name_ua + " " + &passport.middle_name_ua
+}
+
+/// Builds the data of the amendment number `number` to the agreement rendered from `inputs`.
+///
+/// `agreement_created_at` is the creation moment of the amended agreement, while `info`
+/// describes the amendment itself.
+pub fn generate_amendment(
+    inputs: &AgreementInputs,
+    agreement_created_at: DateTime<Utc>,
+    number: i32,
+    changes: &AmendmentChanges,
+    info: &DocumentInfo,
+) -> Result<AmendmentData, ServerError> {
+    let tenant_initials = initials(&inputs.tenant);
+    let landlord_initials = initials(&inputs.landlord);
+
+    let changes = AmendmentTerms {
+        price: changes.price.map(|amount| AmendmentPrice {
+            amount,
+            currency: inputs.rent_data.currency.clone(),
+        }),
+        ending_date: changes
+            .end
+            .map(|end| TypstDateTime(Kyiv.from_utc_datetime(&end))),
+        additional_tenants: changes.additional_tenants.clone(),
+        allowed_animals: changes.allowed_animals.clone(),
+    };
+
+    let document = DocumentMetadata {
+        author: vec![tenant_initials.clone(), landlord_initials.clone()],
+        keywords: vec![
+            format!("agreement:{}", info.agreement_id),
+            format!("template:{}/v{}", info.template_id, info.template_version),
+        ],
+    };
+
+    Ok(AmendmentData {
+        document,
+        number,
+        agreement_date: TypstDateTime(agreement_created_at.with_timezone(&Kyiv)),
+        place: inputs.housing_data.address.city.clone(),
+        date: TypstDateTime(info.created_at.with_timezone(&Kyiv)),
+        tenant_initials,
+        landlord_initials,
+        changes,
+    })
+}
+
+/// Everything the termination act template needs, exposed to it as [`AGREEMENT_DATA_PATH`].
+#[derive(Serialize)]
+pub struct TerminationData {
+    pub document: DocumentMetadata,
+    /// The number of the act among the amendments of the agreement.
+    pub number: i32,
+    /// The date the terminated agreement was concluded.
+    pub agreement_date: TypstDateTime,
+    pub place: String,
+    pub date: TypstDateTime,
+    /// The date the agreement ends on instead of its end date.
+    pub termination_date: TypstDateTime,
+    pub tenant_initials: String,
+    pub landlord_initials: String,
+}
+
+/// Builds the data of the act terminating the agreement rendered from `inputs`
+/// at the end from `changes`.
+///
+/// The act is numbered along with the amendments to the agreement.
+pub fn generate_termination(
+    inputs: &AgreementInputs,
+    agreement_created_at: DateTime<Utc>,
+    number: i32,
+    changes: &AmendmentChanges,
+    info: &DocumentInfo,
+) -> Result<TerminationData, ServerError> {
+    let end = changes
+        .end
+        .ok_or_else(|| ServerError::BadRequest("a termination act must have an end".into()))?;
+    let tenant_initials = initials(&inputs.tenant);
+    let landlord_initials = initials(&inputs.landlord);
+
+    let document = DocumentMetadata {
+        author: vec![tenant_initials.clone(), landlord_initials.clone()],
+        keywords: vec![
+            format!("agreement:{}", info.agreement_id),
+            format!("template:{}/v{}", info.template_id, info.template_version),
+        ],
+    };
+
+    Ok(TerminationData {
+        document,
+        number,
+        agreement_date: TypstDateTime(agreement_created_at.with_timezone(&Kyiv)),
+        place: inputs.housing_data.address.city.clone(),
+        date: TypstDateTime(info.created_at.with_timezone(&Kyiv)),
+        termination_date: TypstDateTime(Kyiv.from_utc_datetime(&end)),
+        tenant_initials,
+        landlord_initials,
+    })
 }
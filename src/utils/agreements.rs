@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use super::{
+    agreement::{generate, AgreementInputs, DocumentInfo},
+    db::{self, AgreementAction, AgreementKey},
+    s3,
+    server_error::ServerError,
+    templates::Template,
+};
+use crate::commands::server::ServerState;
+
+/// How many times the agreement is rendered, when the number it was rendered with
+/// is taken by another agreement of the landlord in the meantime.
+const RENDER_ATTEMPTS: usize = 3;
+
+/// An agreement that was rendered and moved to `generated`.
+pub struct RenderedAgreement {
+    /// The contract number of the agreement.
+    pub number: i32,
+    /// The PDF, yet to be stored.
+    pub pdf: Vec<u8>,
+}
+
+/// Renders the agreement with `template` from the `inputs` stored with it.
+///
+/// Rendering takes a while, so no lock is held meanwhile: the agreement is rendered
+/// with the number it's going to get, then the number is allocated and the transition
+/// is validated and applied in one short transaction. If another agreement of the
+/// landlord took the number in the meantime, the agreement is rendered again.
+///
+/// The PDF is returned after the transaction is committed, so nothing is stored
+/// for a transition that didn't happen. Its hash is stored along with the transition,
+/// the signatures of a previous render are rejected from then on.
+pub async fn render_agreement(
+    state: &ServerState,
+    key: &AgreementKey,
+    template: &Template,
+    created_at: DateTime<Utc>,
+    inputs: &serde_json::Value,
+) -> Result<RenderedAgreement, ServerError> {
+    for _ in 0..RENDER_ATTEMPTS {
+        let number = db::peek_agreement_number(
+            &state.db_pool,
+            key.tenant_id,
+            key.landlord_id,
+            key.housing_id,
+            key.date,
+        )
+        .await?;
+
+        let info = DocumentInfo {
+            agreement_id: key.id.to_string(),
+            template_id: template.id,
+            template_version: template.version,
+            created_at,
+        };
+        let data = generate(
+            serde_json::from_value::<AgreementInputs>(inputs.clone())?,
+            number,
+            &info,
+        )?;
+        let pdf = {
+            let template = template.clone();
+            tokio::task::spawn_blocking(move || template.render(&data, &info)).await??
+        };
+
+        let mut tx = state.db_pool.begin().await?;
+        let allocated = db::allocate_agreement_number(
+            &mut tx,
+            key.tenant_id,
+            key.landlord_id,
+            key.housing_id,
+            key.date,
+        )
+        .await?;
+        // taken by another agreement, the transaction is rolled back on drop
+        if allocated != number {
+            continue;
+        }
+
+        db::apply_transition_in(
+            &mut tx,
+            key.tenant_id,
+            key.landlord_id,
+            key.housing_id,
+            Some(key.date),
+            AgreementAction::Generate {
+                template_id: template.id,
+                template_version: template.version,
+                inputs: inputs.clone(),
+                pdf_sha256: Sha256::digest(&pdf).to_vec(),
+            },
+        )
+        .await?;
+        tx.commit().await?;
+
+        return Ok(RenderedAgreement { number, pdf });
+    }
+
+    Err(ServerError::Conflict(
+        "the contract number was taken by another agreement, try again".into(),
+    ))
+}
+
+/// Renders the already rendered agreement again from what's stored with it, and stores
+/// the PDF unless it's already there.
+///
+/// The agreement is rendered with the pinned template version, its number and creation
+/// moment, so the PDF is the same as the first time. It's for the agreements whose PDF
+/// couldn't be stored right after rendering.
+pub async fn store_rendered_agreement(
+    state: &ServerState,
+    key: &AgreementKey,
+    number: i32,
+) -> Result<(), ServerError> {
+    let snapshot = db::get_agreement_snapshot(
+        &state.db_pool,
+        key.tenant_id,
+        key.landlord_id,
+        key.housing_id,
+        key.date,
+    )
+    .await?
+    .ok_or_else(|| ServerError::Conflict("the agreement was not rendered yet".into()))?;
+
+    let template = state
+        .templates
+        .get(snapshot.template_id, snapshot.template_version)?;
+    let info = DocumentInfo {
+        agreement_id: key.id.to_string(),
+        template_id: template.id,
+        template_version: template.version,
+        created_at: snapshot.created_at,
+    };
+    let data = generate(snapshot.inputs, number, &info)?;
+    let pdf = tokio::task::spawn_blocking(move || template.render(&data, &info)).await??;
+
+    match s3::create_agreement_pdf(state, pdf, key.id).await {
+        Ok(_) | Err(ServerError::Conflict(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{
+    agreement::{
+        generate_amendment, generate_termination, AmendmentChanges, AmendmentKind, DocumentInfo,
+    },
+    db::{self, AgreementAction, AgreementKey, AmendmentKey},
+    s3,
+    server_error::ServerError,
+};
+use crate::commands::server::ServerState;
+
+/// Creates an amendment of `kind` to the signed agreement and renders it.
+///
+/// The amendment refers to the terms the agreement was signed with, updated by its
+/// signed amendments. If rendering fails, the amendment is deleted, so that its
+/// number is taken by the next one.
+pub async fn issue_amendment(
+    state: &ServerState,
+    by: Uuid,
+    agreement: &AgreementKey,
+    kind: AmendmentKind,
+    changes: AmendmentChanges,
+) -> Result<AmendmentKey, ServerError> {
+    let mut snapshot = db::get_agreement_snapshot(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+    )
+    .await?
+    .ok_or_else(|| {
+        ServerError::Conflict(
+            "the terms of the agreement are not stored, it can't be amended".into(),
+        )
+    })?;
+
+    for signed in db::get_signed_amendment_changes(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+    )
+    .await?
+    {
+        signed.apply(&mut snapshot.inputs.rent_data);
+    }
+
+    if kind == AmendmentKind::Termination {
+        let end = changes
+            .end
+            .ok_or_else(|| ServerError::BadRequest("a termination act must have an end".into()))?;
+        if end < Utc::now().naive_utc() {
+            return Err(ServerError::BadRequest(
+                "the agreement can't be terminated in the past".into(),
+            ));
+        }
+        if end >= snapshot.inputs.rent_data.end {
+            return Err(ServerError::BadRequest(format!(
+                "the agreement ends on {} anyway",
+                snapshot.inputs.rent_data.end
+            )));
+        }
+    }
+
+    let template = state.templates.latest(kind.template_id())?;
+
+    let amendment = db::create_amendment(
+        &state.db_pool,
+        agreement.tenant_id,
+        agreement.landlord_id,
+        agreement.housing_id,
+        agreement.date,
+        kind,
+        by,
+    )
+    .await?;
+
+    let key = agreement.amendment(amendment.number);
+    let info = DocumentInfo {
+        agreement_id: format!("{}_amendment_{}", key.agreement_id, key.number),
+        template_id: template.id,
+        template_version: template.version,
+        created_at: amendment.created_at,
+    };
+
+    let rendered = async {
+        let data = match kind {
+            AmendmentKind::Amendment => serde_json::to_value(generate_amendment(
+                &snapshot.inputs,
+                snapshot.created_at,
+                key.number,
+                &changes,
+                &info,
+            )?)?,
+            AmendmentKind::Termination => serde_json::to_value(generate_termination(
+                &snapshot.inputs,
+                snapshot.created_at,
+                key.number,
+                &changes,
+                &info,
+            )?)?,
+        };
+        let pdf = {
+            let template = template.clone();
+            tokio::task::spawn_blocking(move || template.render(&data, &info)).await??
+        };
+        let pdf_sha256 = Sha256::digest(&pdf).to_vec();
+        s3::upload_amendment_pdf(state, pdf, &key).await?;
+
+        Ok::<_, ServerError>(pdf_sha256)
+    }
+    .await;
+
+    // freeing the number, so that the amendment can be created again
+    let pdf_sha256 = match rendered {
+        Ok(pdf_sha256) => pdf_sha256,
+        Err(e) => {
+            db::delete_unrendered_amendment(&state.db_pool, &key).await?;
+            return Err(e);
+        }
+    };
+
+    db::apply_amendment_transition(
+        &state.db_pool,
+        &key,
+        AgreementAction::Generate {
+            template_id: template.id,
+            template_version: template.version,
+            inputs: serde_json::to_value(&changes)?,
+            pdf_sha256,
+        },
+    )
+    .await?;
+
+    Ok(key)
+}
@@ -0,0 +1,46 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use uuid::Uuid;
+
+use crate::commands::server::ServerState;
+
+use super::{db::Party, server_error::ServerError};
+
+/// The user making the request, identified by the bearer token.
+///
+/// The token is verified with the [`TokenVerifier`](super::verify_jwt::TokenVerifier)
+/// of the server, a request without a valid one is rejected with 401.
+pub struct AuthUser(pub Uuid);
+
+impl FromRequestParts<ServerState> for AuthUser {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ServerState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+                .await
+                .map_err(|_| ServerError::Unauthorized("a bearer token is required".into()))?;
+
+        Ok(AuthUser(state.token_verifier.verify(bearer.token()).await?))
+    }
+}
+
+/// Fails unless `uid` is the tenant or the landlord, otherwise returns which one.
+pub fn require_party(uid: Uuid, tenant_id: Uuid, landlord_id: Uuid) -> Result<Party, ServerError> {
+    if uid == tenant_id {
+        Ok(Party::Tenant)
+    } else if uid == landlord_id {
+        Ok(Party::Landlord)
+    } else {
+        Err(ServerError::Forbidden(
+            "you are not authorized to perform this action: you're not a landlord or a tenant"
+                .into(),
+        ))
+    }
+}
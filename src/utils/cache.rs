@@ -0,0 +1,78 @@
+use std::{io::ErrorKind, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::server_error::ServerError;
+
+/// Where the proposals are kept between the restarts of the server.
+pub const CACHE_SAVE_LOCATION_DEFAULT: &str = "./cache.json";
+
+/// How long a proposal waits for the other party to confirm it.
+const PROPOSAL_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// The parties and the housing an agreement is proposed for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AgreementProposalKey {
+    pub tenant_id: String,
+    pub landlord_id: String,
+    pub housing_id: String,
+}
+
+/// Which parties confirmed the proposal.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct AgreementProposalValue {
+    pub tenant_confirmed: bool,
+    pub landlord_confirmed: bool,
+}
+
+/// A cache of agreement proposals, an agreement is generated once both parties confirm it.
+pub type AgreementProposalCache = Cache<AgreementProposalKey, Arc<AgreementProposalValue>>;
+
+pub fn build_cache() -> AgreementProposalCache {
+    Cache::builder().time_to_live(PROPOSAL_TTL).build()
+}
+
+/// Fills the cache with the proposals saved by [`save_cache_to_file`].
+///
+/// A missing file means there is nothing to restore.
+pub async fn populate_cache_from_file(
+    path: &str,
+    cache: &AgreementProposalCache,
+) -> Result<(), ServerError> {
+    let content = match tokio::fs::read(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => Err(e).context("cannot read the cache")?,
+    };
+
+    let proposals: Vec<(AgreementProposalKey, AgreementProposalValue)> =
+        serde_json::from_slice(&content).context("cannot parse the cache")?;
+    info!("restoring {} agreement proposals", proposals.len());
+
+    for (key, value) in proposals {
+        cache.insert(key, Arc::new(value)).await;
+    }
+
+    Ok(())
+}
+
+/// Saves the proposals, so that they outlive a restart of the server.
+pub async fn save_cache_to_file(
+    path: &str,
+    cache: &AgreementProposalCache,
+) -> Result<(), ServerError> {
+    let proposals: Vec<(AgreementProposalKey, AgreementProposalValue)> = cache
+        .iter()
+        .map(|(key, value)| (key.as_ref().clone(), *value))
+        .collect();
+
+    let content = serde_json::to_vec(&proposals)?;
+    tokio::fs::write(path, content)
+        .await
+        .context("cannot save the cache")?;
+
+    Ok(())
+}
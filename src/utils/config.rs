@@ -1,6 +1,7 @@
 use std::{ffi::c_int, fs};
 
 use serde::Deserialize;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EUSignConfig {
@@ -30,10 +31,76 @@ pub struct DiiaConfig {
     pub offer_signing_id: String,
 }
 
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AdminConfig {
+    /// Users allowed to call the `/admin` routes.
+    pub uids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct TypstConfig {
+    /// A directory with vendored packages, laid out as `<namespace>/<name>/<version>`.
+    /// It's populated by the `packages` command.
+    pub packages_dir: Option<String>,
+    /// Fail instead of downloading the packages that aren't vendored.
+    #[serde(default)]
+    pub offline: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct NotificationsConfig {
+    /// URLs every [`Notification`](super::notifications::Notification) is posted to as JSON.
+    pub webhooks: Vec<String>,
+}
+
+/// How the tokens the users authenticate with are verified.
+///
+/// The subject of every token must be the UUID of the user.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum AuthConfig {
+    /// ID tokens of the Firebase project of the Google service account.
+    #[default]
+    Firebase,
+    /// ID tokens of an AWS Cognito user pool.
+    Cognito {
+        region: String,
+        user_pool_id: String,
+        /// The app client the tokens are issued to.
+        client_id: String,
+    },
+    /// Tokens signed with any of the keys published at `jwks_url`.
+    Jwks {
+        jwks_url: String,
+        issuer: String,
+        audience: String,
+    },
+    /// Tokens minted by the `test-token` command with the key at `key_path`.
+    /// Never use it outside of testing.
+    #[cfg(feature = "dev")]
+    Local {
+        #[serde(default = "default_local_key_path")]
+        key_path: String,
+    },
+}
+
+#[cfg(feature = "dev")]
+fn default_local_key_path() -> String {
+    super::local_issuer::DEFAULT_KEY_PATH.into()
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub eusign: EUSignConfig,
     pub diia: DiiaConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub typst: TypstConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
 impl Config {
@@ -1,16 +1,21 @@
 use anyhow::{anyhow, Context};
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json;
+use sha2::{Digest, Sha256};
 use sqlx::{
+    migrate::Migrator,
     postgres::{PgConnectOptions, PgPoolOptions},
-    Pool, Postgres, Row,
+    Pool, Postgres, Row, Transaction,
 };
 use uuid::Uuid;
 use std::sync::Arc;
 // use sqlx::types::Uuid;
-use crate::utils::eusign::{DocumentUnit, InternalPassport, TaxpayerCard};
+use crate::utils::agreement::{
+    AgreementInputs, AmendmentChanges, AmendmentKind, AGREEMENT_INPUTS_VERSION,
+};
+use crate::utils::eusign::{DocumentUnit, InternalPassport, SignatureReport, TaxpayerCard};
 use crate::utils::server_error::ServerError;
+use crate::utils::templates::TemplateId;
 
 pub type DbPool = Pool<Postgres>;
 
@@ -61,66 +66,18 @@ pub async fn init_db_pool(
         .map_err(|e| anyhow!("{e}").into())
 }
 
-/// Perform initial database setup - create tables if they don't exist
-pub async fn setup_db(pool: &DbPool) -> Result<(), ServerError> {
-    // enum to represent agreement state
-    sqlx::query(
-        r#"
-        DO $$
-        BEGIN
-            IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'agreement_state') THEN
-                CREATE TYPE agreement_state AS ENUM (
-                'not_initiated','initiated','rejected',
-                'generated','half_signed','signed','expired'
-                );
-            END IF;
-        END$$;
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Table for DocumentUnits
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS document_units (
-            user_id TEXT PRIMARY KEY,
-            taxpayer_card JSONB NOT NULL,
-            internal_passport JSONB NOT NULL,
-            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .context("Failed to create document_units table")?;
+/// The migrations from `deploy/db/migrations`, embedded into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./deploy/db/migrations");
 
-    // Table for Agreements
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS agreements (
-            tenant_id          UUID NOT NULL,
-            landlord_id        UUID NOT NULL,
-            housing_id         UUID NOT NULL,
-            date               DATE NOT NULL DEFAULT NOW(),
-
-            state              agreement_state NOT NULL DEFAULT 'not_initiated',
-            action_by          UUID,
-            half_signature     TEXT,
-            tenant_signature   TEXT,
-            landlord_signature TEXT,
-
-            PRIMARY KEY (tenant_id, landlord_id, housing_id, date),
-
-            CHECK (state <> 'initiated'    OR action_by IS NOT NULL),
-            CHECK (state <> 'rejected'     OR action_by IS NOT NULL),
-            CHECK (state <> 'half_signed'  OR (action_by IS NOT NULL AND half_signature IS NOT NULL)),
-            CHECK (state <> 'signed'       OR (tenant_signature IS NOT NULL AND landlord_signature IS NOT NULL)),
-            CHECK (state <> 'expired'      OR (tenant_signature IS NOT NULL AND landlord_signature IS NOT NULL))
-        );
-        "#
-    ).execute(pool).await
-    .context("Failed to create agreements table")?;
+/// Applies the pending migrations.
+///
+/// Fails if an applied migration was changed or removed since,
+/// which is detected by the checksums stored in `_sqlx_migrations`.
+pub async fn run_migrations(pool: &DbPool) -> Result<(), ServerError> {
+    MIGRATOR
+        .run(pool)
+        .await
+        .context("Failed to run the database migrations")?;
 
     Ok(())
 }
@@ -188,7 +145,7 @@ pub async fn get_document_unit_from_db(
 }
 
 /// Delete a document unit from the database
-pub async fn delete_document_unit(pool: &DbPool, user_id: Uuid) -> Result<bool, ServerError> {
+pub async fn delete_document_unit(pool: &DbPool, user_id: &str) -> Result<bool, ServerError> {
     let result = sqlx::query(
         r#"
         DELETE FROM document_units
@@ -203,344 +160,1812 @@ pub async fn delete_document_unit(pool: &DbPool, user_id: Uuid) -> Result<bool,
     Ok(result.rows_affected() > 0)
 }
 
-#[derive(Serialize, Deserialize)]
+/// An agreement as stored, without what it was rendered from.
+#[derive(Serialize, Deserialize, sqlx::FromRow)]
 pub struct Agreement {
+    pub id: Uuid,
     pub tenant_id: Uuid,
     pub landlord_id: Uuid,
     pub housing_id: Uuid,
     pub date: NaiveDate,
-    pub state: String,              // keep as String ⇒ maps 1‑to‑1 to enum labels
+    pub state: AgreementState,
     pub action_by: Option<Uuid>,
     pub half_signature: Option<String>,
     pub tenant_signature: Option<String>,
     pub landlord_signature: Option<String>,
 }
 
-/// Create a new agreement in the database
-pub async fn create_agreement(pool: &DbPool, agreement: &Agreement) -> Result<(), ServerError> {
-    sqlx::query(
-        r#"
-        INSERT INTO agreements (
-            tenant_id, 
-            landlord_id, 
-            housing_id, 
-            date
-        )
-        VALUES ($1, $2, $3, $4)
-        ON CONFLICT DO NOTHING
-        "#,
-    )
-    .bind(&agreement.tenant_id)
-    .bind(&agreement.landlord_id)
-    .bind(&agreement.housing_id)
-    .bind(agreement.date)
-    .execute(pool)
-    .await
-    .context("Failed to insert agreement")?;
+/// The state of an agreement, mirroring the `agreement_state` enum in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "agreement_state", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AgreementState {
+    NotInitiated,
+    Initiated,
+    Rejected,
+    Generated,
+    HalfSigned,
+    Signed,
+    Expired,
+    Terminated,
+}
 
-    Ok(())
+/// A side of the agreement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Tenant,
+    Landlord,
 }
 
-/// Retrieve a specific agreement from the database
-pub async fn get_agreement(
+/// An action that moves an agreement from one state to another.
+#[derive(Debug, Clone)]
+pub enum AgreementAction {
+    /// One of the parties initiates (or re-initiates a rejected) proposal.
+    Initiate { by: Uuid },
+    /// One of the parties rejects the proposal.
+    Reject { by: Uuid, reason: Option<String> },
+    /// The PDF of the agreement was rendered with the given template from `inputs`.
+    Generate {
+        template_id: TemplateId,
+        template_version: i32,
+        /// Serialized [`AgreementInputs`].
+        inputs: serde_json::Value,
+        /// The SHA-256 of the rendered PDF, the one the parties sign.
+        pdf_sha256: Vec<u8>,
+    },
+    /// One of the parties signed the rendered agreement.
+    Sign {
+        by: Uuid,
+        party: Party,
+        signature: String,
+        /// Serialized [`SignatureReport`].
+        report: serde_json::Value,
+    },
+    /// The agreement is past its end date.
+    Expire,
+    /// The agreement is past the date set by its signed termination act.
+    Terminate,
+}
+
+impl AgreementAction {
+    /// The name of the action, as recorded in the audit log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AgreementAction::Initiate { .. } => "initiate",
+            AgreementAction::Reject { .. } => "reject",
+            AgreementAction::Generate { .. } => "generate",
+            AgreementAction::Sign { .. } => "sign",
+            AgreementAction::Expire => "expire",
+            AgreementAction::Terminate => "terminate",
+        }
+    }
+
+    /// The user that performed the action, if any.
+    pub fn by(&self) -> Option<Uuid> {
+        match self {
+            AgreementAction::Initiate { by }
+            | AgreementAction::Reject { by, .. }
+            | AgreementAction::Sign { by, .. } => Some(*by),
+            AgreementAction::Generate { .. }
+            | AgreementAction::Expire
+            | AgreementAction::Terminate => None,
+        }
+    }
+}
+
+impl AgreementState {
+    /// Returns the state the agreement moves to after `action`.
+    ///
+    /// `signed_by` is the party whose signature is already stored, if any.
+    /// Illegal moves, like signing an unrendered agreement or signing twice,
+    /// are reported as a conflict.
+    pub fn transition(
+        self,
+        action: &AgreementAction,
+        signed_by: Option<Party>,
+    ) -> Result<AgreementState, ServerError> {
+        use AgreementState::*;
+
+        let next = match (self, action) {
+            (NotInitiated | Rejected, AgreementAction::Initiate { .. }) => Initiated,
+            (NotInitiated | Initiated | Generated, AgreementAction::Reject { .. }) => Rejected,
+            (Initiated | Generated, AgreementAction::Generate { .. }) => Generated,
+            (Generated, AgreementAction::Sign { .. }) => HalfSigned,
+            (HalfSigned, AgreementAction::Sign { party, .. }) if signed_by != Some(*party) => {
+                Signed
+            }
+            (HalfSigned, AgreementAction::Sign { .. }) => {
+                return Err(ServerError::Conflict(
+                    "the agreement is already signed by this party".into(),
+                ))
+            }
+            (Signed, AgreementAction::Expire) => Expired,
+            (Signed, AgreementAction::Terminate) => Terminated,
+            (state, action) => {
+                return Err(ServerError::Conflict(format!(
+                    "cannot {} an agreement in state `{}`",
+                    action.name(),
+                    state.as_str()
+                )))
+            }
+        };
+
+        Ok(next)
+    }
+
+    /// The label of the state in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AgreementState::NotInitiated => "not_initiated",
+            AgreementState::Initiated => "initiated",
+            AgreementState::Rejected => "rejected",
+            AgreementState::Generated => "generated",
+            AgreementState::HalfSigned => "half_signed",
+            AgreementState::Signed => "signed",
+            AgreementState::Expired => "expired",
+            AgreementState::Terminated => "terminated",
+        }
+    }
+}
+
+/// Applies `action` to the latest agreement between the parties.
+///
+/// The row is locked for the duration of the transaction, the transition is validated
+/// and an audit row is written to `agreement_events`.
+pub async fn apply_transition(
     pool: &DbPool,
-    tenant_id: &str,
-    landlord_id: &str,
-    housing_id: &str,
-    date: &NaiveDate,
-) -> Result<Option<Agreement>, ServerError> {
-    let record = sqlx::query(
-        r#"
-        SELECT tenant_id, landlord_id, housing_id, date
-        FROM agreements 
-        WHERE tenant_id = $1
-          AND landlord_id = $2
-          AND housing_id = $3
-          AND date = $4
-        "#,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    action: AgreementAction,
+) -> Result<AgreementState, ServerError> {
+    let mut tx = pool.begin().await?;
+    let state =
+        apply_transition_in(&mut tx, tenant_id, landlord_id, housing_id, None, action).await?;
+    tx.commit().await?;
+
+    Ok(state)
+}
+
+/// Same as [`apply_transition`], but to the agreement concluded on `date`,
+/// which is not necessarily the latest one.
+pub async fn apply_transition_on(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+    action: AgreementAction,
+) -> Result<AgreementState, ServerError> {
+    let mut tx = pool.begin().await?;
+    let state = apply_transition_in(
+        &mut tx,
+        tenant_id,
+        landlord_id,
+        housing_id,
+        Some(date),
+        action,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(state)
+}
+
+/// Same as [`apply_transition`], but inside an already opened transaction.
+///
+/// If `date` is `None`, the latest agreement between the parties is considered.
+pub async fn apply_transition_in(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: Option<NaiveDate>,
+    action: AgreementAction,
+) -> Result<AgreementState, ServerError> {
+    transition_in(
+        tx,
+        tenant_id,
+        landlord_id,
+        housing_id,
+        TransitionTarget::Agreement(date),
+        action,
     )
-    .bind(tenant_id)
-    .bind(landlord_id)
-    .bind(housing_id)
-    .bind(date)
-    .fetch_optional(pool)
     .await
-    .context("Failed to fetch the agreement")?;
+}
 
-    if let Some(row) = record {
-        let tenant_id: Uuid = row.try_get("tenant_id")?;
-        let landlord_id: Uuid = row.try_get("landlord_id")?;
-        let housing_id: Uuid = row.try_get("housing_id")?;
-        let date: NaiveDate = row.try_get("date")?;
-        let state: String = row.try_get("state")?;
-        let action_by: Option<Uuid> = row.try_get("action_by")?;
-        let half_signature: Option<String> = row.try_get("half_signature")?;
-        let tenant_signature: Option<String> = row.try_get("tenant_signature")?;
-        let landlord_signature: Option<String> = row.try_get("landlord_signature")?;
+/// The row of `agreements` or `amendments` a transition is applied to.
+#[derive(Clone, Copy)]
+enum TransitionTarget {
+    /// The agreement concluded on the date, the latest one if `None`.
+    Agreement(Option<NaiveDate>),
+    /// The amendment with the number to the agreement concluded on the date.
+    Amendment(NaiveDate, i32),
+}
 
+impl TransitionTarget {
+    fn noun(self) -> &'static str {
+        match self {
+            TransitionTarget::Agreement(_) => "agreement",
+            TransitionTarget::Amendment(..) => "amendment",
+        }
+    }
 
-        Ok(Some(Agreement {
-            tenant_id,
-            landlord_id,
-            housing_id,
-            date,
-            state,
-            action_by,
-            half_signature,
-            tenant_signature,
-            landlord_signature,
-        }))
-    } else {
-        Ok(None)
+    fn table(self) -> &'static str {
+        match self {
+            TransitionTarget::Agreement(_) => "agreements",
+            TransitionTarget::Amendment(..) => "amendments",
+        }
+    }
+
+    /// The condition on the amendment number, bound as `$5`, on top of the parties and the date.
+    fn number_filter(self) -> &'static str {
+        match self {
+            TransitionTarget::Agreement(_) => "",
+            TransitionTarget::Amendment(..) => "AND number = $5",
+        }
+    }
+
+    fn date(self) -> Option<NaiveDate> {
+        match self {
+            TransitionTarget::Agreement(date) => date,
+            TransitionTarget::Amendment(date, _) => Some(date),
+        }
+    }
+
+    fn amendment(self) -> Option<i32> {
+        match self {
+            TransitionTarget::Amendment(_, number) => Some(number),
+            TransitionTarget::Agreement(_) => None,
+        }
     }
 }
 
-/// Retrieve all agreements for a specific tenant
-pub async fn get_agreements_for_tenant(
-    pool: &DbPool,
-    tenant_id: &str,
-) -> Result<Vec<Agreement>, ServerError> {
-    let rows = sqlx::query(
+/// Locks the row of the target, validates the transition, applies it and writes
+/// an audit row to `agreement_events`.
+///
+/// The keys of the row are bound first, as `$1`-`$4` and, for amendments, `$5`,
+/// the arguments of the action follow.
+async fn transition_in(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    target: TransitionTarget,
+    action: AgreementAction,
+) -> Result<AgreementState, ServerError> {
+    let (noun, table, number_filter) = (target.noun(), target.table(), target.number_filter());
+
+    let lock = format!(
         r#"
-        SELECT tenant_id, landlord_id, housing_id, date
-        FROM agreements 
+        SELECT date, state, tenant_signature, landlord_signature
+        FROM {table}
         WHERE tenant_id = $1
+          AND landlord_id = $2
+          AND housing_id = $3
+          AND ($4::DATE IS NULL OR date = $4)
+          {number_filter}
         ORDER BY date DESC
-        "#,
-    )
-    .bind(tenant_id)
-    .fetch_all(pool)
-    .await
-    .context("Failed to fetch tenant agreements")?;
+        LIMIT 1
+        FOR UPDATE
+        "#
+    );
+    let lock = sqlx::query(&lock)
+        .bind(tenant_id)
+        .bind(landlord_id)
+        .bind(housing_id)
+        .bind(target.date());
+    let lock = match target.amendment() {
+        Some(number) => lock.bind(number),
+        None => lock,
+    };
 
-    let mut agreements = Vec::new();
-    for row in rows {
-        let tenant_id: Uuid = row.try_get("tenant_id")?;
-        let landlord_id: Uuid = row.try_get("landlord_id")?;
-        let housing_id: Uuid = row.try_get("housing_id")?;
-        let date: NaiveDate = row.try_get("date")?;
-        let state: String = row.try_get("state")?;
-        let action_by: Option<Uuid> = row.try_get("action_by")?;
-        let half_signature: Option<String> = row.try_get("half_signature")?;
-        let tenant_signature: Option<String> = row.try_get("tenant_signature")?;
-        let landlord_signature: Option<String> = row.try_get("landlord_signature")?;
+    let row = lock
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to lock the {noun}"))?
+        .ok_or_else(|| ServerError::NotFound(format!("no such {noun}")))?;
 
+    let date: NaiveDate = row.try_get("date")?;
+    let state: AgreementState = row.try_get("state")?;
+    let tenant_signature: Option<String> = row.try_get("tenant_signature")?;
+    let landlord_signature: Option<String> = row.try_get("landlord_signature")?;
 
+    let signed_by = match (tenant_signature, landlord_signature) {
+        (Some(_), None) => Some(Party::Tenant),
+        (None, Some(_)) => Some(Party::Landlord),
+        _ => None,
+    };
 
-        agreements.push(Agreement {
-            tenant_id,
-            landlord_id,
-            housing_id,
-            date,
-            state,
-            action_by,
-            half_signature,
-            tenant_signature,
-            landlord_signature,
-        });
+    let next = state.transition(&action, signed_by)?;
+
+    // the arguments of the action are numbered after the keys
+    let keys = 4 + usize::from(target.amendment().is_some());
+    let [state_arg, arg1, arg2, arg3, arg4, arg5] =
+        [1, 2, 3, 4, 5, 6].map(|i| format!("${}", keys + i));
+
+    let set = match (&action, target) {
+        (AgreementAction::Initiate { .. }, TransitionTarget::Agreement(_)) => format!(
+            r#"
+            state = {state_arg},
+            action_by = {arg1},
+            rejection_reason = NULL,
+            initiated_at = NOW()
+            "#
+        ),
+        (AgreementAction::Reject { .. }, _) => format!(
+            r#"
+            state = {state_arg},
+            action_by = {arg1},
+            rejection_reason = {arg2},
+            rejected_at = NOW()
+            "#
+        ),
+        (AgreementAction::Generate { .. }, TransitionTarget::Agreement(_)) => format!(
+            r#"
+            state = {state_arg},
+            template_id = {arg1},
+            template_version = {arg2},
+            inputs = {arg3},
+            inputs_version = {arg4},
+            pdf_sha256 = {arg5},
+            generated_at = NOW()
+            "#
+        ),
+        (AgreementAction::Generate { .. }, TransitionTarget::Amendment(..)) => format!(
+            r#"
+            state = {state_arg},
+            template_id = {arg1},
+            template_version = {arg2},
+            changes = {arg3},
+            pdf_sha256 = {arg4},
+            generated_at = NOW()
+            "#
+        ),
+        (AgreementAction::Sign { party, .. }, _) => {
+            let col = match party {
+                Party::Tenant => "tenant_signature",
+                Party::Landlord => "landlord_signature",
+            };
+            let at_col = match next {
+                AgreementState::HalfSigned => "half_signed_at",
+                _ => "signed_at",
+            };
+            // the first signer is kept in `action_by` and `half_signature`
+            format!(
+                r#"
+                state = {state_arg},
+                {col} = {arg2},
+                {col}_report = {arg3},
+                half_signature = COALESCE(half_signature, {arg2}),
+                action_by = CASE WHEN {state_arg} = 'half_signed' THEN {arg1} ELSE action_by END,
+                {at_col} = NOW()
+                "#
+            )
+        }
+        (AgreementAction::Expire, TransitionTarget::Agreement(_)) => {
+            format!("state = {state_arg}, expired_at = NOW()")
+        }
+        (AgreementAction::Terminate, TransitionTarget::Agreement(_)) => {
+            format!("state = {state_arg}, terminated_at = NOW()")
+        }
+        // amendments are created already initiated and end along with their agreement
+        (
+            AgreementAction::Initiate { .. } | AgreementAction::Expire | AgreementAction::Terminate,
+            TransitionTarget::Amendment(..),
+        ) => {
+            return Err(ServerError::Conflict(format!(
+                "cannot {} an amendment",
+                action.name()
+            )))
+        }
+    };
+
+    let sql = format!(
+        r#"
+        UPDATE {table}
+        SET {set}
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          {number_filter}
+        "#
+    );
+    let update = sqlx::query(&sql)
+        .bind(tenant_id)
+        .bind(landlord_id)
+        .bind(housing_id)
+        .bind(date);
+    let update = match target.amendment() {
+        Some(number) => update.bind(number),
+        None => update,
     }
+    .bind(next);
 
-    Ok(agreements)
-}
+    let update = match &action {
+        AgreementAction::Initiate { by } => update.bind(*by),
+        AgreementAction::Reject { by, reason } => update.bind(*by).bind(reason.clone()),
+        AgreementAction::Sign {
+            by,
+            signature,
+            report,
+            ..
+        } => update
+            .bind(*by)
+            .bind(signature.clone())
+            .bind(report.clone()),
+        AgreementAction::Generate {
+            template_id,
+            template_version,
+            inputs,
+            pdf_sha256,
+        } => {
+            let update = update
+                .bind(template_id.as_str())
+                .bind(*template_version)
+                .bind(inputs.clone());
+            match target {
+                TransitionTarget::Agreement(_) => update.bind(AGREEMENT_INPUTS_VERSION),
+                TransitionTarget::Amendment(..) => update,
+            }
+            .bind(pdf_sha256.clone())
+        }
+        AgreementAction::Expire | AgreementAction::Terminate => update,
+    };
 
-/// Retrieve all agreements for a specific landlord
-pub async fn get_agreements_for_landlord(
-    pool: &DbPool,
-    landlord_id: &str,
-) -> Result<Vec<Agreement>, ServerError> {
-    let rows = sqlx::query(
+    update
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to update the {noun} state"))?;
+
+    // the requests to sign the previous render can't be answered anymore
+    if matches!(action, AgreementAction::Generate { .. }) {
+        sqlx::query(
+            r#"
+            DELETE FROM diia_requests
+            WHERE kind = 'signature'
+              AND agreement_id = (
+                  SELECT id FROM agreements
+                  WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+              )
+              AND amendment IS NOT DISTINCT FROM $5
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(landlord_id)
+        .bind(housing_id)
+        .bind(date)
+        .bind(target.amendment())
+        .execute(&mut **tx)
+        .await
+        .context("Failed to delete the outstanding signature requests")?;
+    }
+
+    let details = match (&action, target) {
+        (AgreementAction::Reject { reason, .. }, _) => serde_json::json!({ "reason": reason }),
+        (AgreementAction::Sign { party, .. }, _) => {
+            serde_json::json!({ "party": format!("{party:?}") })
+        }
+        // only the hash of the inputs, as they hold the personal data of the parties
+        (
+            AgreementAction::Generate {
+                template_id,
+                template_version,
+                inputs,
+                ..
+            },
+            TransitionTarget::Agreement(_),
+        ) => serde_json::json!({
+            "template_id": template_id,
+            "template_version": template_version,
+            "inputs_version": AGREEMENT_INPUTS_VERSION,
+            "inputs_sha256": inputs_sha256(inputs),
+        }),
+        (
+            AgreementAction::Generate {
+                template_id,
+                template_version,
+                inputs,
+                ..
+            },
+            TransitionTarget::Amendment(..),
+        ) => serde_json::json!({
+            "template_id": template_id,
+            "template_version": template_version,
+            "changes_sha256": inputs_sha256(inputs),
+        }),
+        _ => serde_json::Value::Null,
+    };
+
+    sqlx::query(
         r#"
-        SELECT tenant_id, landlord_id, housing_id, date
-        FROM agreements 
-        WHERE landlord_id = $1
-        ORDER BY date DESC
+        INSERT INTO agreement_events (
+            tenant_id, landlord_id, housing_id, date, amendment,
+            from_state, to_state, action, action_by, details
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         "#,
     )
+    .bind(tenant_id)
     .bind(landlord_id)
-    .fetch_all(pool)
+    .bind(housing_id)
+    .bind(date)
+    .bind(target.amendment())
+    .bind(state)
+    .bind(next)
+    .bind(action.name())
+    .bind(action.by())
+    .bind(details)
+    .execute(&mut **tx)
     .await
-    .context("Failed to fetch landlord agreements")?;
-
-    let mut agreements = Vec::new();
-    for row in rows {
-        let tenant_id: Uuid = row.try_get("tenant_id")?;
-        let landlord_id: Uuid = row.try_get("landlord_id")?;
-        let housing_id: Uuid = row.try_get("housing_id")?;
-        let date: NaiveDate = row.try_get("date")?;
-        let state: String = row.try_get("state")?;
-        let action_by: Option<Uuid> = row.try_get("action_by")?;
-        let half_signature: Option<String> = row.try_get("half_signature")?;
-        let tenant_signature: Option<String> = row.try_get("tenant_signature")?;
-        let landlord_signature: Option<String> = row.try_get("landlord_signature")?;
+    .with_context(|| format!("Failed to record the {noun} event"))?;
 
+    Ok(next)
+}
 
+/// The hex SHA-256 of the inputs the document was rendered from.
+///
+/// The audit trail records it instead of the inputs, so that the personal data stays
+/// in the agreement row only, while the renders can still be told apart.
+fn inputs_sha256(inputs: &serde_json::Value) -> String {
+    format!("{:x}", Sha256::digest(inputs.to_string()))
+}
 
-        agreements.push(Agreement {
-            tenant_id,
-            landlord_id,
-            housing_id,
-            date,
-            state,
-            action_by,
-            half_signature,
-            tenant_signature,
-            landlord_signature,
-        });
-    }
+/// Moments at which the agreement went through each of its states.
+#[derive(Serialize, Deserialize)]
+pub struct AgreementTransitions {
+    pub created_at: DateTime<Utc>,
+    pub initiated_at: Option<DateTime<Utc>>,
+    pub rejected_at: Option<DateTime<Utc>>,
+    pub generated_at: Option<DateTime<Utc>>,
+    pub half_signed_at: Option<DateTime<Utc>>,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub expired_at: Option<DateTime<Utc>>,
+    pub terminated_at: Option<DateTime<Utc>>,
+}
 
-    Ok(agreements)
+/// The current state of an agreement together with its transition history.
+#[derive(Serialize, Deserialize)]
+pub struct AgreementStateRecord {
+    pub id: Uuid,
+    pub date: NaiveDate,
+    pub state: AgreementState,
+    pub action_by: Option<Uuid>,
+    /// The id of the agreement this one renews, if any.
+    pub renewal_of: Option<Uuid>,
+    /// The contract number among the agreements of the landlord, once rendered.
+    pub number: Option<i32>,
+    pub transitions: AgreementTransitions,
 }
 
-pub async fn get_agreements_for_tenant_and_landlord(
+/// Retrieve the state of an agreement.
+///
+/// If `date` is `None`, the latest agreement between the parties is considered.
+pub async fn get_agreement_state(
     pool: &DbPool,
-    tenant_id: &str,
-    landlord_id: &str,
-) -> Result<Vec<Agreement>, ServerError> {
-    let rows = sqlx::query(
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: Option<NaiveDate>,
+) -> Result<Option<AgreementStateRecord>, ServerError> {
+    let record = sqlx::query(
         r#"
-        SELECT tenant_id, landlord_id, housing_id, date
+        SELECT id,
+               date,
+               state,
+               action_by,
+               renewal_of,
+               number,
+               created_at,
+               initiated_at,
+               rejected_at,
+               generated_at,
+               half_signed_at,
+               signed_at,
+               expired_at,
+               terminated_at
         FROM agreements
         WHERE tenant_id = $1
           AND landlord_id = $2
+          AND housing_id = $3
+          AND ($4::DATE IS NULL OR date = $4)
         ORDER BY date DESC
+        LIMIT 1
         "#,
     )
     .bind(tenant_id)
     .bind(landlord_id)
-    .fetch_all(pool)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_optional(pool)
     .await
-    .context("Failed to fetch tenant-landlord agreements")?;
-
-    let mut agreements = Vec::new();
-    for row in rows {
-        let tenant_id: Uuid = row.try_get("tenant_id")?;
-        let landlord_id: Uuid = row.try_get("landlord_id")?;
-        let housing_id: Uuid = row.try_get("housing_id")?;
-        let date: NaiveDate = row.try_get("date")?;
-        let state: String = row.try_get("state")?;
-        let action_by: Option<Uuid> = row.try_get("action_by")?;
-        let half_signature: Option<String> = row.try_get("half_signature")?;
-        let tenant_signature: Option<String> = row.try_get("tenant_signature")?;
-        let landlord_signature: Option<String> = row.try_get("landlord_signature")?;
-
+    .context("Failed to fetch the agreement state")?;
 
+    let Some(row) = record else {
+        return Ok(None);
+    };
 
-        agreements.push(Agreement {
-            tenant_id,
-            landlord_id,
-            housing_id,
-            date,
-            state,
-            action_by,
-            half_signature,
-            tenant_signature,
-            landlord_signature,
-        });
-    }
+    Ok(Some(AgreementStateRecord {
+        id: row.try_get("id")?,
+        date: row.try_get("date")?,
+        state: row.try_get("state")?,
+        action_by: row.try_get("action_by")?,
+        renewal_of: row.try_get("renewal_of")?,
+        number: row.try_get("number")?,
+        transitions: AgreementTransitions {
+            created_at: row.try_get("created_at")?,
+            initiated_at: row.try_get("initiated_at")?,
+            rejected_at: row.try_get("rejected_at")?,
+            generated_at: row.try_get("generated_at")?,
+            half_signed_at: row.try_get("half_signed_at")?,
+            signed_at: row.try_get("signed_at")?,
+            expired_at: row.try_get("expired_at")?,
+            terminated_at: row.try_get("terminated_at")?,
+        },
+    }))
+}
 
-    Ok(agreements)
+/// Everything the agreement was rendered from.
+pub struct AgreementSnapshot {
+    pub template_id: TemplateId,
+    pub template_version: i32,
+    pub created_at: DateTime<Utc>,
+    pub inputs: AgreementInputs,
 }
 
-/// Delete an agreement from the database
-pub async fn delete_agreement(
+/// Returns what the agreement was rendered from, to re-render it or to read its terms.
+///
+/// `None` means that no PDF was rendered for the agreement yet.
+pub async fn get_agreement_snapshot(
     pool: &DbPool,
-    tenant_id: &str,
-    landlord_id: &str,
-    housing_id: &str,
-    date: &NaiveDate,
-) -> Result<bool, ServerError> {
-    let result = sqlx::query(
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+) -> Result<Option<AgreementSnapshot>, ServerError> {
+    let row = sqlx::query(
         r#"
-        DELETE FROM agreements 
-        WHERE tenant_id = $1
-          AND landlord_id = $2
-          AND housing_id = $3
-          AND date = $4
+        SELECT template_id, template_version, created_at, inputs, inputs_version
+        FROM agreements
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
         "#,
     )
     .bind(tenant_id)
     .bind(landlord_id)
     .bind(housing_id)
     .bind(date)
-    .execute(pool)
+    .fetch_optional(pool)
     .await
-    .context("Failed to delete agreement")?;
+    .context("Failed to fetch the agreement snapshot")?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
 
-    Ok(result.rows_affected() > 0)
+    let template_id: Option<String> = row.try_get("template_id")?;
+    let template_version: Option<i32> = row.try_get("template_version")?;
+    let inputs: Option<sqlx::types::Json<AgreementInputs>> = row.try_get("inputs")?;
+    let inputs_version: Option<i32> = row.try_get("inputs_version")?;
+
+    let (Some(template_id), Some(template_version), Some(inputs)) =
+        (template_id, template_version, inputs)
+    else {
+        return Ok(None);
+    };
+
+    if inputs_version != Some(AGREEMENT_INPUTS_VERSION) {
+        return Err(anyhow!(
+            "agreement inputs of version {inputs_version:?} can't be read, expected {AGREEMENT_INPUTS_VERSION}"
+        )
+        .into());
+    }
+
+    Ok(Some(AgreementSnapshot {
+        template_id: template_id.parse()?,
+        template_version,
+        created_at: row.try_get("created_at")?,
+        inputs: inputs.0,
+    }))
 }
 
-/// Delete the latest agreement from the database
-pub async fn delete_latest_agreement(
+/// Create a new agreement in the database and initiate it on behalf of `initiated_by`.
+///
+/// If the agreement for today already exists, only the initiation is applied to it.
+pub async fn create_agreement(
     pool: &DbPool,
     tenant_id: Uuid,
     landlord_id: Uuid,
     housing_id: Uuid,
-) -> Result<bool, ServerError> {
-    let result = sqlx::query(
+    initiated_by: Uuid,
+) -> Result<AgreementState, ServerError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
         r#"
-        DELETE FROM agreements
-        WHERE tenant_id = $1
-          AND landlord_id = $2
-          AND housing_id = $3
-          AND date = (
-              SELECT MAX(date)
-              FROM agreements
-              WHERE tenant_id = $1
-                AND landlord_id = $2
-                AND housing_id = $3
-          )
+        INSERT INTO agreements (
+            tenant_id,
+            landlord_id,
+            housing_id
+        )
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
         "#,
     )
     .bind(tenant_id)
     .bind(landlord_id)
     .bind(housing_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await
-    .context("Failed to delete latest agreement")?;
+    .context("Failed to insert agreement")?;
 
-    Ok(result.rows_affected() > 0)
-}
+    let state = apply_transition_in(
+        &mut tx,
+        tenant_id,
+        landlord_id,
+        housing_id,
+        None,
+        AgreementAction::Initiate { by: initiated_by },
+    )
+    .await?;
 
-pub struct SignatureEntry {
-    pub tenant_id: Uuid,
-    pub landlord_id: Uuid,
-    pub housing_id: Uuid,
-    pub tenant_signature: String,
-    pub landlord_signature: String,
+    tx.commit().await?;
+
+    Ok(state)
 }
 
-pub async fn persist_signature(
+/// Returns the contract number [`allocate_agreement_number`] would return now,
+/// without taking it or locking anything.
+///
+/// The agreement is rendered with it before the number is allocated, see
+/// [`render_agreement`](super::agreements::render_agreement).
+pub async fn peek_agreement_number(
     pool: &DbPool,
     tenant_id: Uuid,
     landlord_id: Uuid,
     housing_id: Uuid,
-    signed_by: Uuid,
-    signature: String,
-) -> Result<(), ServerError> {
-    // decide which column to update and which new state to apply
-    let (col, new_state) = if signed_by == tenant_id {
-        ("tenant_signature", "half_signed")
-    } else {
-        ("landlord_signature", "signed") // second signer => fully signed
+    date: NaiveDate,
+) -> Result<i32, ServerError> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(a.number, COALESCE(n.last_number, 0) + 1)
+        FROM agreements a
+        LEFT JOIN agreement_numbers n ON n.landlord_id = a.landlord_id
+        WHERE a.tenant_id = $1 AND a.landlord_id = $2 AND a.housing_id = $3 AND a.date = $4
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read the agreement number")?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))
+}
+
+/// Returns the contract number of the agreement concluded on `date`,
+/// allocating the next number of the landlord if it has none yet.
+///
+/// The agreement row and the counter of the landlord stay locked until `tx` is
+/// committed, so a rolled back transition gives its number to the next agreement.
+pub async fn allocate_agreement_number(
+    tx: &mut Transaction<'_, Postgres>,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+) -> Result<i32, ServerError> {
+    let number: Option<i32> = sqlx::query_scalar(
+        r#"
+        SELECT number
+        FROM agreements
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+        FOR UPDATE
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_optional(&mut **tx)
+    .await
+    .context("Failed to lock the agreement")?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    if let Some(number) = number {
+        return Ok(number);
+    }
+
+    let number: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO agreement_numbers (landlord_id, last_number)
+        VALUES ($1, 1)
+        ON CONFLICT (landlord_id)
+        DO UPDATE SET last_number = agreement_numbers.last_number + 1
+        RETURNING last_number
+        "#,
+    )
+    .bind(landlord_id)
+    .fetch_one(&mut **tx)
+    .await
+    .context("Failed to allocate the agreement number")?;
+
+    sqlx::query(
+        r#"
+        UPDATE agreements
+        SET number = $5
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .bind(number)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to store the agreement number")?;
+
+    Ok(number)
+}
+
+/// Find the agreement of the landlord by its contract number.
+pub async fn find_agreement_by_number(
+    pool: &DbPool,
+    landlord_id: Uuid,
+    number: i32,
+) -> Result<Option<AgreementKey>, ServerError> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, tenant_id, landlord_id, housing_id, date
+        FROM agreements
+        WHERE landlord_id = $1 AND number = $2
+        "#,
+    )
+    .bind(landlord_id)
+    .bind(number)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to find the agreement")?;
+
+    row.as_ref().map(agreement_key_from_row).transpose()
+}
+
+/// Create an agreement renewing the one with the id `renewal_of`,
+/// initiated on behalf of `initiated_by`.
+///
+/// The new agreement is dated today, so an agreement can't be renewed on the day it's created.
+pub async fn create_renewal(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    renewal_of: Uuid,
+    initiated_by: Uuid,
+) -> Result<AgreementStateRecord, ServerError> {
+    let mut tx = pool.begin().await?;
+
+    let date: NaiveDate = sqlx::query_scalar(
+        r#"
+        INSERT INTO agreements (
+            tenant_id,
+            landlord_id,
+            housing_id,
+            renewal_of
+        )
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        RETURNING date
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(renewal_of)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to insert agreement")?
+    .ok_or_else(|| {
+        ServerError::Conflict("an agreement between the parties was already created today".into())
+    })?;
+
+    apply_transition_in(
+        &mut tx,
+        tenant_id,
+        landlord_id,
+        housing_id,
+        Some(date),
+        AgreementAction::Initiate { by: initiated_by },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    get_agreement_state(pool, tenant_id, landlord_id, housing_id, Some(date))
+        .await?
+        .ok_or_else(|| anyhow!("the renewal disappeared right after its creation").into())
+}
+
+/// Delete an agreement that was never rendered, e.g. when rendering of its renewal failed.
+pub async fn delete_unrendered_agreement(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+) -> Result<bool, ServerError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM agreements
+        WHERE tenant_id = $1
+          AND landlord_id = $2
+          AND housing_id = $3
+          AND date = $4
+          AND state = 'initiated'
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .execute(pool)
+    .await
+    .context("Failed to delete agreement")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Selects the agreements matching every given filter, the latest first.
+///
+/// This is the only query the columns of [`Agreement`] are listed in.
+async fn select_agreements(
+    pool: &DbPool,
+    tenant_id: Option<Uuid>,
+    landlord_id: Option<Uuid>,
+    housing_id: Option<Uuid>,
+    date: Option<NaiveDate>,
+) -> Result<Vec<Agreement>, ServerError> {
+    let agreements = sqlx::query_as!(
+        Agreement,
+        r#"
+        SELECT id,
+               tenant_id,
+               landlord_id,
+               housing_id,
+               date,
+               state AS "state: AgreementState",
+               action_by,
+               half_signature,
+               tenant_signature,
+               landlord_signature
+        FROM agreements
+        WHERE ($1::UUID IS NULL OR tenant_id = $1)
+          AND ($2::UUID IS NULL OR landlord_id = $2)
+          AND ($3::UUID IS NULL OR housing_id = $3)
+          AND ($4::DATE IS NULL OR date = $4)
+        ORDER BY date DESC
+        "#,
+        tenant_id,
+        landlord_id,
+        housing_id,
+        date,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the agreements")?;
+
+    Ok(agreements)
+}
+
+/// Retrieve a specific agreement from the database
+pub async fn get_agreement(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+) -> Result<Option<Agreement>, ServerError> {
+    let agreements = select_agreements(
+        pool,
+        Some(tenant_id),
+        Some(landlord_id),
+        Some(housing_id),
+        Some(date),
+    )
+    .await?;
+
+    Ok(agreements.into_iter().next())
+}
+
+/// Retrieve all agreements for a specific tenant
+pub async fn get_agreements_for_tenant(
+    pool: &DbPool,
+    tenant_id: Uuid,
+) -> Result<Vec<Agreement>, ServerError> {
+    select_agreements(pool, Some(tenant_id), None, None, None).await
+}
+
+/// Retrieve all agreements for a specific landlord
+pub async fn get_agreements_for_landlord(
+    pool: &DbPool,
+    landlord_id: Uuid,
+) -> Result<Vec<Agreement>, ServerError> {
+    select_agreements(pool, None, Some(landlord_id), None, None).await
+}
+
+/// Retrieve all agreements between a specific tenant and landlord
+pub async fn get_agreements_for_tenant_and_landlord(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+) -> Result<Vec<Agreement>, ServerError> {
+    select_agreements(pool, Some(tenant_id), Some(landlord_id), None, None).await
+}
+
+/// Delete the agreement with the given id, together with its amendments.
+pub async fn delete_agreement_by_id(pool: &DbPool, id: Uuid) -> Result<bool, ServerError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM agreements
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .context("Failed to delete agreement")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Both signatures of a signed agreement, to be combined into a single file.
+pub struct SignatureEntry {
+    /// The agreement that was signed, not necessarily the latest one between the parties.
+    pub agreement_id: Uuid,
+    pub tenant_id: Uuid,
+    pub landlord_id: Uuid,
+    pub housing_id: Uuid,
+    pub tenant_signature: String,
+    pub landlord_signature: String,
+}
+
+/// Persist the signature of one of the parties, along with its verification report.
+///
+/// Whether the agreement becomes `half_signed` or `signed` depends on
+/// the signatures already stored, not on the order of the parties.
+/// The Diia request the signature answers is taken in the same transaction,
+/// so it's used up only if the signature is stored.
+pub async fn persist_signature(
+    pool: &DbPool,
+    key: &AgreementKey,
+    token_hash: &[u8],
+    signed_by: Uuid,
+    signature: String,
+    report: &SignatureReport,
+) -> Result<AgreementState, ServerError> {
+    let party = party_of(key.tenant_id, key.landlord_id, signed_by)?;
+    let report =
+        serde_json::to_value(report).context("Failed to serialize the signature report")?;
+
+    let mut tx = pool.begin().await?;
+    let file_hash = take_answered_diia_request(&mut tx, token_hash).await?;
+    let pdf_sha256: Option<Vec<u8>> =
+        sqlx::query_scalar("SELECT pdf_sha256 FROM agreements WHERE id = $1")
+            .bind(key.id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to fetch the hash of the agreement")?
+            .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+    check_signed_pdf(pdf_sha256, &file_hash)?;
+
+    let state = apply_transition_in(
+        &mut tx,
+        key.tenant_id,
+        key.landlord_id,
+        key.housing_id,
+        Some(key.date),
+        AgreementAction::Sign {
+            by: signed_by,
+            party,
+            signature,
+            report,
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(state)
+}
+
+/// Returns both signatures of the signed agreement.
+pub async fn get_signature_entry(
+    pool: &DbPool,
+    key: &AgreementKey,
+) -> Result<SignatureEntry, ServerError> {
+    let row = sqlx::query(
+        r#"
+        SELECT tenant_signature, landlord_signature
+        FROM agreements
+        WHERE id = $1
+        "#,
+    )
+    .bind(key.id)
+    .fetch_one(pool)
+    .await
+    .context("Failed to fetch the agreement signatures")?;
+
+    Ok(SignatureEntry {
+        agreement_id: key.id,
+        tenant_id: key.tenant_id,
+        landlord_id: key.landlord_id,
+        housing_id: key.housing_id,
+        tenant_signature: row.try_get("tenant_signature")?,
+        landlord_signature: row.try_get("landlord_signature")?,
+    })
+}
+
+/// Which party `signed_by` is.
+fn party_of(tenant_id: Uuid, landlord_id: Uuid, signed_by: Uuid) -> Result<Party, ServerError> {
+    if signed_by == tenant_id {
+        Ok(Party::Tenant)
+    } else if signed_by == landlord_id {
+        Ok(Party::Landlord)
+    } else {
+        Err(ServerError::BadRequest(
+            "the signer is neither a tenant nor a landlord".into(),
+        ))
+    }
+}
+
+/// Identifies an agreement both by its id and by its key in the database.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AgreementKey {
+    #[serde(rename = "agreement_id")]
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub landlord_id: Uuid,
+    pub housing_id: Uuid,
+    pub date: NaiveDate,
+}
+
+impl AgreementKey {
+    /// The key of the amendment `number` to this agreement.
+    pub fn amendment(&self, number: i32) -> AmendmentKey {
+        AmendmentKey {
+            agreement_id: self.id,
+            tenant_id: self.tenant_id,
+            landlord_id: self.landlord_id,
+            housing_id: self.housing_id,
+            date: self.date,
+            number,
+        }
+    }
+}
+
+/// Returns the key of the agreement with the given id.
+pub async fn get_agreement_key(pool: &DbPool, id: Uuid) -> Result<AgreementKey, ServerError> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, tenant_id, landlord_id, housing_id, date
+        FROM agreements
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the agreement key")?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    agreement_key_from_row(&row)
+}
+
+fn agreement_key_from_row(row: &sqlx::postgres::PgRow) -> Result<AgreementKey, ServerError> {
+    Ok(AgreementKey {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        landlord_id: row.try_get("landlord_id")?,
+        housing_id: row.try_get("housing_id")?,
+        date: row.try_get("date")?,
+    })
+}
+
+/// Identifies an amendment: the agreement it amends and its number within it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AmendmentKey {
+    pub agreement_id: Uuid,
+    pub tenant_id: Uuid,
+    pub landlord_id: Uuid,
+    pub housing_id: Uuid,
+    pub date: NaiveDate,
+    pub number: i32,
+}
+
+/// An amendment (addendum) to a signed agreement.
+#[derive(Serialize, Deserialize)]
+pub struct AmendmentRecord {
+    pub number: i32,
+    pub kind: AmendmentKind,
+    pub state: AgreementState,
+    pub action_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// The changed terms, set once the amendment is rendered.
+    pub changes: Option<AmendmentChanges>,
+}
+
+/// Creates the next amendment to the signed agreement, initiated by `initiated_by`.
+///
+/// Amendments, including termination acts, are numbered from 1 within the agreement.
+/// Only one amendment at a time can be in progress, so that the changes don't overlap,
+/// and none can be created once a termination act is signed.
+pub async fn create_amendment(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+    kind: AmendmentKind,
+    initiated_by: Uuid,
+) -> Result<AmendmentRecord, ServerError> {
+    let mut tx = pool.begin().await?;
+
+    // locking the agreement serializes the numbering of its amendments
+    let state: AgreementState = sqlx::query_scalar(
+        r#"
+        SELECT state
+        FROM agreements
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+        FOR UPDATE
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to lock the agreement")?
+    .ok_or_else(|| ServerError::NotFound("no such agreement".into()))?;
+
+    if state != AgreementState::Signed {
+        return Err(ServerError::Conflict(format!(
+            "only a signed agreement can be amended, this one is `{}`",
+            state.as_str()
+        )));
+    }
+
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE state IN ('initiated', 'generated', 'half_signed')) > 0
+                AS pending,
+            COUNT(*) FILTER (WHERE state = 'signed' AND kind = 'termination') > 0
+                AS terminated
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to fetch the pending amendments")?;
+
+    if row.try_get("pending")? {
+        return Err(ServerError::Conflict(
+            "the previous amendment to the agreement is not signed or rejected yet".into(),
+        ));
+    }
+    if row.try_get("terminated")? {
+        return Err(ServerError::Conflict(
+            "the agreement is terminated, it can't be amended anymore".into(),
+        ));
+    }
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO amendments (tenant_id, landlord_id, housing_id, date, number, kind, action_by)
+        SELECT $1, $2, $3, $4, COALESCE(MAX(number), 0) + 1, $5, $6
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+        RETURNING number, state, action_by, created_at
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .bind(kind.as_str())
+    .bind(initiated_by)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to insert amendment")?;
+
+    let record = AmendmentRecord {
+        number: row.try_get("number")?,
+        kind,
+        state: row.try_get("state")?,
+        action_by: row.try_get("action_by")?,
+        created_at: row.try_get("created_at")?,
+        changes: None,
     };
 
-    let query = format!(
-        "UPDATE agreements
-         SET {col} = $4,
-             state = $5,
-             action_by = COALESCE(action_by, $3)
-         WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3
-           AND state <> 'expired'"
-    );
+    sqlx::query(
+        r#"
+        INSERT INTO agreement_events (
+            tenant_id, landlord_id, housing_id, date, amendment,
+            from_state, to_state, action, action_by
+        )
+        VALUES ($1, $2, $3, $4, $5, 'not_initiated', $6, 'initiate', $7)
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .bind(record.number)
+    .bind(record.state)
+    .bind(initiated_by)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to record the amendment event")?;
 
-    sqlx::query(&query)
-        .bind(tenant_id)
-        .bind(landlord_id)
-        .bind(housing_id)
-        .bind(signature)
-        .bind(new_state)
-        .execute(pool)
-        .await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// Retrieve an amendment, `None` if there is no such amendment.
+pub async fn get_amendment(
+    pool: &DbPool,
+    key: &AmendmentKey,
+) -> Result<Option<AmendmentRecord>, ServerError> {
+    let record = sqlx::query(
+        r#"
+        SELECT number, kind, state, action_by, created_at, changes
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          AND number = $5
+        "#,
+    )
+    .bind(key.tenant_id)
+    .bind(key.landlord_id)
+    .bind(key.housing_id)
+    .bind(key.date)
+    .bind(key.number)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the amendment")?;
+
+    let Some(row) = record else {
+        return Ok(None);
+    };
+
+    let kind: String = row.try_get("kind")?;
+    let changes: Option<sqlx::types::Json<AmendmentChanges>> = row.try_get("changes")?;
+
+    Ok(Some(AmendmentRecord {
+        number: row.try_get("number")?,
+        kind: kind.parse()?,
+        state: row.try_get("state")?,
+        action_by: row.try_get("action_by")?,
+        created_at: row.try_get("created_at")?,
+        changes: changes.map(|changes| changes.0),
+    }))
+}
+
+/// Returns the changes of the signed amendments to the agreement, in the order of signing.
+///
+/// Applied on top of the stored inputs of the agreement, they give its current terms.
+/// Termination acts are not included, as they change nothing but end the agreement.
+pub async fn get_signed_amendment_changes(
+    pool: &DbPool,
+    tenant_id: Uuid,
+    landlord_id: Uuid,
+    housing_id: Uuid,
+    date: NaiveDate,
+) -> Result<Vec<AmendmentChanges>, ServerError> {
+    let changes: Vec<sqlx::types::Json<AmendmentChanges>> = sqlx::query_scalar(
+        r#"
+        SELECT changes
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          AND state = 'signed'
+          AND kind = 'amendment'
+        ORDER BY number
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(landlord_id)
+    .bind(housing_id)
+    .bind(date)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the signed amendments")?;
+
+    Ok(changes.into_iter().map(|changes| changes.0).collect())
+}
+
+/// Deletes an amendment that was never rendered, so that its number is reused.
+pub async fn delete_unrendered_amendment(
+    pool: &DbPool,
+    key: &AmendmentKey,
+) -> Result<bool, ServerError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          AND number = $5
+          AND state = 'initiated'
+        "#,
+    )
+    .bind(key.tenant_id)
+    .bind(key.landlord_id)
+    .bind(key.housing_id)
+    .bind(key.date)
+    .bind(key.number)
+    .execute(pool)
+    .await
+    .context("Failed to delete amendment")?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Applies `action` to the amendment, same as [`apply_transition`] does to agreements.
+///
+/// Amendments are created already initiated and never expire on their own,
+/// hence only rendering, signing and rejection apply to them.
+pub async fn apply_amendment_transition(
+    pool: &DbPool,
+    key: &AmendmentKey,
+    action: AgreementAction,
+) -> Result<AgreementState, ServerError> {
+    let mut tx = pool.begin().await?;
+    let state = transition_in(
+        &mut tx,
+        key.tenant_id,
+        key.landlord_id,
+        key.housing_id,
+        TransitionTarget::Amendment(key.date, key.number),
+        action,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(state)
+}
+
+/// Both signatures of a signed amendment, to be combined into a single file.
+pub struct AmendmentSignatureEntry {
+    pub key: AmendmentKey,
+    pub tenant_signature: String,
+    pub landlord_signature: String,
+}
+
+/// Persist the signature of one of the parties under the amendment,
+/// along with its verification report.
+///
+/// Once both parties signed, the signatures are returned to be combined.
+/// The Diia request is taken the same way [`persist_signature`] takes it.
+pub async fn persist_amendment_signature(
+    pool: &DbPool,
+    key: &AmendmentKey,
+    token_hash: &[u8],
+    signed_by: Uuid,
+    signature: String,
+    report: &SignatureReport,
+) -> Result<Option<AmendmentSignatureEntry>, ServerError> {
+    let party = party_of(key.tenant_id, key.landlord_id, signed_by)?;
+    let report =
+        serde_json::to_value(report).context("Failed to serialize the signature report")?;
+
+    let mut tx = pool.begin().await?;
+    let file_hash = take_answered_diia_request(&mut tx, token_hash).await?;
+    let pdf_sha256: Option<Vec<u8>> = sqlx::query_scalar(
+        r#"
+        SELECT pdf_sha256
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          AND number = $5
+        "#,
+    )
+    .bind(key.tenant_id)
+    .bind(key.landlord_id)
+    .bind(key.housing_id)
+    .bind(key.date)
+    .bind(key.number)
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to fetch the hash of the amendment")?
+    .ok_or_else(|| ServerError::NotFound("no such amendment".into()))?;
+    check_signed_pdf(pdf_sha256, &file_hash)?;
+
+    let state = transition_in(
+        &mut tx,
+        key.tenant_id,
+        key.landlord_id,
+        key.housing_id,
+        TransitionTarget::Amendment(key.date, key.number),
+        AgreementAction::Sign {
+            by: signed_by,
+            party,
+            signature,
+            report,
+        },
+    )
+    .await?;
+
+    if state != AgreementState::Signed {
+        tx.commit().await?;
+        return Ok(None);
+    }
+
+    let row = sqlx::query(
+        r#"
+        SELECT tenant_signature, landlord_signature
+        FROM amendments
+        WHERE tenant_id = $1 AND landlord_id = $2 AND housing_id = $3 AND date = $4
+          AND number = $5
+        "#,
+    )
+    .bind(key.tenant_id)
+    .bind(key.landlord_id)
+    .bind(key.housing_id)
+    .bind(key.date)
+    .bind(key.number)
+    .fetch_one(&mut *tx)
+    .await
+    .context("Failed to fetch the amendment signatures")?;
+    tx.commit().await?;
+
+    Ok(Some(AmendmentSignatureEntry {
+        key: *key,
+        tenant_signature: row.try_get("tenant_signature")?,
+        landlord_signature: row.try_get("landlord_signature")?,
+    }))
+}
+
+/// A signed agreement that reached its end.
+pub struct EndedAgreement {
+    pub key: AgreementKey,
+    /// Whether it ended by a signed termination act rather than at its end date.
+    pub terminated: bool,
+}
+
+/// Returns the signed agreements that end before `now`.
+///
+/// The end is the date of the signed termination act, if any, otherwise the end
+/// set by the latest signed amendment, otherwise the end the agreement was signed with.
+/// Agreements without stored inputs have no known end and never end this way.
+pub async fn get_ended_agreements(
+    pool: &DbPool,
+    now: NaiveDateTime,
+) -> Result<Vec<EndedAgreement>, ServerError> {
+    let rows = sqlx::query(
+        r#"
+        SELECT a.id,
+               a.tenant_id,
+               a.landlord_id,
+               a.housing_id,
+               a.date,
+               t.ends_at IS NOT NULL AS terminated
+        FROM agreements a
+        LEFT JOIN LATERAL (
+            SELECT (m.changes->>'end')::TIMESTAMP AS ends_at
+            FROM amendments m
+            WHERE m.tenant_id = a.tenant_id AND m.landlord_id = a.landlord_id
+              AND m.housing_id = a.housing_id AND m.date = a.date
+              AND m.state = 'signed'
+              AND m.kind = 'termination'
+            LIMIT 1
+        ) t ON TRUE
+        LEFT JOIN LATERAL (
+            SELECT (m.changes->>'end')::TIMESTAMP AS ends_at
+            FROM amendments m
+            WHERE m.tenant_id = a.tenant_id AND m.landlord_id = a.landlord_id
+              AND m.housing_id = a.housing_id AND m.date = a.date
+              AND m.state = 'signed'
+              AND m.kind = 'amendment'
+              AND m.changes->>'end' IS NOT NULL
+            ORDER BY m.number DESC
+            LIMIT 1
+        ) e ON TRUE
+        WHERE a.state = 'signed'
+          AND COALESCE(t.ends_at, e.ends_at, (a.inputs->'rent_data'->>'end')::TIMESTAMP) < $1
+        "#,
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the ended agreements")?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(EndedAgreement {
+                key: agreement_key_from_row(&row)?,
+                terminated: row.try_get("terminated")?,
+            })
+        })
+        .collect()
+}
+
+/// A request sent to Diia, as stored until the callback answering it arrives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiiaRequest {
+    /// The user is asked to share their documents.
+    Sharing { user_id: Uuid },
+    /// The user is asked to sign the agreement, or its amendment if `amendment` is set.
+    Signature {
+        signed_by: Uuid,
+        agreement_id: Uuid,
+        amendment: Option<i32>,
+        /// The SHA-256 of the PDF sent to be signed.
+        file_hash: Vec<u8>,
+    },
+}
+
+impl DiiaRequest {
+    /// The name of the kind of the request, as stored in the database.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DiiaRequest::Sharing { .. } => "sharing",
+            DiiaRequest::Signature { .. } => "signature",
+        }
+    }
+}
+
+/// Stores the request under the hash of its token, dropping the expired ones.
+pub async fn store_diia_request(
+    pool: &DbPool,
+    token_hash: &[u8],
+    request: &DiiaRequest,
+    expires_at: DateTime<Utc>,
+) -> Result<(), ServerError> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM diia_requests WHERE expires_at <= NOW()")
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete the expired Diia requests")?;
+
+    let (user_id, agreement_id, amendment, file_hash) = match request {
+        DiiaRequest::Sharing { user_id } => (*user_id, None, None, None),
+        DiiaRequest::Signature {
+            signed_by,
+            agreement_id,
+            amendment,
+            file_hash,
+        } => (
+            *signed_by,
+            Some(*agreement_id),
+            *amendment,
+            Some(file_hash.as_slice()),
+        ),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO diia_requests
+            (token_hash, kind, user_id, agreement_id, amendment, file_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(token_hash)
+    .bind(request.kind())
+    .bind(user_id)
+    .bind(agreement_id)
+    .bind(amendment)
+    .bind(file_hash)
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await
+    .context("Failed to store the Diia request")?;
+
+    tx.commit().await?;
 
     Ok(())
 }
+
+/// Returns the request stored under the hash of its token, leaving it in place,
+/// `None` if there is no such request or it has expired.
+pub async fn get_diia_request(
+    pool: &DbPool,
+    token_hash: &[u8],
+) -> Result<Option<DiiaRequest>, ServerError> {
+    let row = sqlx::query(
+        r#"
+        SELECT kind, user_id, agreement_id, amendment, file_hash
+        FROM diia_requests
+        WHERE token_hash = $1 AND expires_at > NOW()
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the Diia request")?;
+
+    row.as_ref().map(diia_request_from_row).transpose()
+}
+
+/// Deletes the request stored under the hash of its token and returns it,
+/// `None` if there is no such request or it has expired.
+pub async fn take_diia_request(
+    pool: &DbPool,
+    token_hash: &[u8],
+) -> Result<Option<DiiaRequest>, ServerError> {
+    let mut tx = pool.begin().await?;
+    let request = take_diia_request_in(&mut tx, token_hash).await?;
+    tx.commit().await?;
+
+    Ok(request)
+}
+
+/// Same as [`take_diia_request`], but inside an already opened transaction.
+pub async fn take_diia_request_in(
+    tx: &mut Transaction<'_, Postgres>,
+    token_hash: &[u8],
+) -> Result<Option<DiiaRequest>, ServerError> {
+    let row = sqlx::query(
+        r#"
+        DELETE FROM diia_requests
+        WHERE token_hash = $1
+        RETURNING kind, user_id, agreement_id, amendment, file_hash, expires_at > NOW() AS valid
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(&mut **tx)
+    .await
+    .context("Failed to take the Diia request")?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    if !row.try_get::<bool, _>("valid")? {
+        return Ok(None);
+    }
+
+    diia_request_from_row(&row).map(Some)
+}
+
+/// Takes the request a stored signature answers, it must not have been answered meanwhile.
+///
+/// Returns the SHA-256 of the PDF the request was issued for.
+async fn take_answered_diia_request(
+    tx: &mut Transaction<'_, Postgres>,
+    token_hash: &[u8],
+) -> Result<Vec<u8>, ServerError> {
+    match take_diia_request_in(tx, token_hash).await? {
+        Some(DiiaRequest::Signature { file_hash, .. }) => Ok(file_hash),
+        Some(DiiaRequest::Sharing { .. }) => Err(ServerError::Unauthorized(
+            "the request id is not of a signature request".into(),
+        )),
+        None => Err(ServerError::Unauthorized(
+            "the request id is unknown or expired".into(),
+        )),
+    }
+}
+
+/// Rejects a signature over a PDF other than the one the document was rendered into last.
+///
+/// The documents rendered before their hash was stored have none to compare with.
+fn check_signed_pdf(pdf_sha256: Option<Vec<u8>>, file_hash: &[u8]) -> Result<(), ServerError> {
+    match pdf_sha256 {
+        Some(pdf_sha256) if pdf_sha256 != file_hash => Err(ServerError::Conflict(
+            "the document was rendered again since it was sent to be signed".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn diia_request_from_row(row: &sqlx::postgres::PgRow) -> Result<DiiaRequest, ServerError> {
+    let user_id = row.try_get("user_id")?;
+    let request = match row.try_get::<&str, _>("kind")? {
+        "sharing" => DiiaRequest::Sharing { user_id },
+        "signature" => DiiaRequest::Signature {
+            signed_by: user_id,
+            agreement_id: row.try_get("agreement_id")?,
+            amendment: row.try_get("amendment")?,
+            file_hash: row.try_get("file_hash")?,
+        },
+        kind => return Err(anyhow!("unknown Diia request kind `{kind}`").into()),
+    };
+
+    Ok(request)
+}
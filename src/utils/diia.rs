@@ -4,18 +4,27 @@ use std::{
 };
 
 use anyhow::{anyhow, Context};
-use base64::{prelude::BASE64_STANDARD, Engine as _};
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chrono::{Duration, Utc};
 use http::{
     header::{ACCEPT, AUTHORIZATION},
     HeaderMap, HeaderValue,
 };
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::Deserialize;
-use tracing::info;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
 
 use super::{
-    db::SignatureEntry,
+    db::{
+        get_diia_request, store_diia_request, take_diia_request, AmendmentSignatureEntry, DbPool,
+        DiiaRequest, SignatureEntry,
+    },
     eusign::*,
-    s3::{get_agreement_pdf, upload_agreement_p7s},
+    s3::{get_agreement_pdf, get_amendment_pdf, upload_agreement_p7s, upload_amendment_p7s},
     server_error::{EUSignError, ServerError},
 };
 use crate::commands::server::ServerState;
@@ -57,31 +66,119 @@ pub async fn refresh_diia_session_token(state: ServerState) -> Result<(), Server
     Ok(())
 }
 
+/// How long Diia has to call back after a request is sent.
+const DIIA_REQUEST_TTL: Duration = Duration::hours(1);
+
+/// Stores the request and returns the token to send to Diia as its request id.
+///
+/// The token is random, so a callback can't be forged without the request it answers.
+pub async fn issue_diia_request(
+    pool: &DbPool,
+    request: &DiiaRequest,
+) -> Result<String, ServerError> {
+    let mut token = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut token)
+        .map_err(|_| anyhow!("cannot generate a Diia request token"))?;
+    let token = BASE64_URL_SAFE_NO_PAD.encode(token);
+
+    store_diia_request(
+        pool,
+        &diia_token_hash(&token),
+        request,
+        Utc::now() + DIIA_REQUEST_TTL,
+    )
+    .await?;
+
+    Ok(token)
+}
+
+/// The hash the request with this token is stored under.
+pub fn diia_token_hash(token: &str) -> Vec<u8> {
+    Sha256::digest(token).to_vec()
+}
+
+/// Returns the request the callback with this request id answers, leaving it in place.
+///
+/// An unknown, expired or already used token is rejected. The request is taken once
+/// what the callback brings is stored, see [`persist_signature`](super::db::persist_signature).
+pub async fn find_diia_request_by_token(
+    pool: &DbPool,
+    token: &str,
+) -> Result<DiiaRequest, ServerError> {
+    get_diia_request(pool, &diia_token_hash(token))
+        .await?
+        .ok_or_else(unknown_diia_request)
+}
+
+/// Returns the request the callback with this request id answers.
+///
+/// Every request is answered once, an unknown, expired or already used token is rejected.
+pub async fn take_diia_request_by_token(
+    pool: &DbPool,
+    token: &str,
+) -> Result<DiiaRequest, ServerError> {
+    take_diia_request(pool, &diia_token_hash(token))
+        .await?
+        .ok_or_else(unknown_diia_request)
+}
+
+fn unknown_diia_request() -> ServerError {
+    warn!("Diia callback with an unknown or expired request id");
+    ServerError::Unauthorized("the request id is unknown or expired".into())
+}
+
 /// Adds two CAdES signatures and stores the signed file on S3.
 pub async fn diia_signature_handler(
     state: ServerState,
     SignatureEntry {
-        tenant_id,
-        landlord_id,
-        housing_id,
+        agreement_id,
         tenant_signature,
         landlord_signature,
+        ..
     }: SignatureEntry,
 ) -> Result<(), ServerError> {
-    // 1) fetch the PDF
-    let mut pdf = get_agreement_pdf(
-        &state,
-        tenant_id, landlord_id, housing_id,
-    )
-    .await?;
+    let mut pdf = get_agreement_pdf(&state, agreement_id).await?;
+
+    let signed = combine_signatures(&mut pdf, &tenant_signature, &landlord_signature)?;
+
+    upload_agreement_p7s(&state, signed, agreement_id).await?;
+
+    Ok(())
+}
+
+/// Same as [`diia_signature_handler`], but for an amendment to the agreement.
+pub async fn amendment_signature_handler(
+    state: ServerState,
+    AmendmentSignatureEntry {
+        key,
+        tenant_signature,
+        landlord_signature,
+    }: AmendmentSignatureEntry,
+) -> Result<(), ServerError> {
+    let mut pdf = get_amendment_pdf(&state, &key).await?;
+
+    let signed = combine_signatures(&mut pdf, &tenant_signature, &landlord_signature)?;
+
+    upload_amendment_p7s(&state, signed, &key).await?;
+
+    Ok(())
+}
+
+/// Puts both Base64-encoded signatures of the PDF into a single CAdES container.
+fn combine_signatures(
+    pdf: &mut [u8],
+    tenant_signature: &str,
+    landlord_signature: &str,
+) -> Result<Vec<u8>, ServerError> {
     let pdf_data = pdf.as_mut_ptr();
 
-    // 2) decode both Base64 blobs
+    // decode both Base64 blobs
     let mut tenant_sig_bytes = BASE64_STANDARD
-        .decode(&tenant_signature)
+        .decode(tenant_signature)
         .context("unable to decode tenant signature")?;
     let mut landlord_sig_bytes = BASE64_STANDARD
-        .decode(&landlord_signature)
+        .decode(landlord_signature)
         .context("unable to decode landlord signature")?;
 
     unsafe {
@@ -284,16 +381,6 @@ pub async fn diia_signature_handler(
         // 5) free the last C++ buffer
         EUFreeMemory(final_ptr);
 
-        // 6) upload
-        upload_agreement_p7s(
-            &state,
-            out,
-            tenant_id,
-            landlord_id,
-            housing_id,
-        )
-        .await?;
+        Ok(out)
     }
-
-    Ok(())
 }
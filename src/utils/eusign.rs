@@ -10,6 +10,7 @@ use std::io::Read;
 use std::ptr;
 
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use tracing::error;
 use tracing::warn;
@@ -167,6 +168,22 @@ pub unsafe fn Initialize(config: Config) -> Result<(), EUSignError> {
         EU_SIGN_TYPE_LENGTH.into(),
     );
 
+    // The names in the certificates are compared with the ones from Diia documents
+    let nStringEncoding: c_int = EU_UTF8_ENCODING as c_int;
+    let nUseUTCTime: c_int = 1;
+
+    EUSetRuntimeParameter(
+        EU_STRING_ENCODING_PARAMETER.as_ptr() as *mut c_char,
+        &nStringEncoding as *const _ as *mut c_void,
+        EU_STRING_ENCODING_PARAMETER_LENGTH.into(),
+    );
+
+    EUSetRuntimeParameter(
+        EU_USE_UTC_TIME_PARAMETER.as_ptr() as *mut c_char,
+        &nUseUTCTime as *const _ as *mut c_void,
+        EU_USE_UTC_TIME_PARAMETER_LENGTH.into(),
+    );
+
     EUSetUIMode(0);
 
     EUSetModeSettings(0);
@@ -426,6 +443,216 @@ pub unsafe fn decrypt_customer_data(
     Ok(customerData)
 }
 
+/// What was established about a signature of one of the parties, kept along with it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignatureReport {
+    /// Base64 of the SHA-256 of the signed PDF.
+    pub file_hash: String,
+    pub signer_full_name: String,
+    pub signer_tax_number: String,
+    pub issuer: String,
+    /// The serial number of the certificate of the signer.
+    pub serial: String,
+    /// When the signature was made, if it carries a signing time.
+    pub signed_at: Option<DateTime<Utc>>,
+    /// Whether the signing time comes from a time stamp.
+    pub time_stamped: bool,
+    /// When the certificate was found to be good by OCSP.
+    pub verified_at: DateTime<Utc>,
+}
+
+/// Verifies the Base64-encoded CAdES signature of a party to an agreement:
+/// 1. the signature is valid and made over `hash`, the SHA-256 of the signed PDF;
+/// 2. the certificate of the signer is good according to OCSP;
+/// 3. the certificate is issued to the person from `party`'s documents.
+///
+/// Any mismatch is reported as a bad request.
+pub fn verify_signature(
+    hash: &[u8],
+    signature: &str,
+    party: &DocumentUnit,
+) -> Result<SignatureReport, ServerError> {
+    let mut hash = hash.to_vec();
+    let mut signature = STANDARD
+        .decode(signature)
+        .map_err(|_| ServerError::BadRequest("the signature is not valid Base64".into()))?;
+    let signature_len = signature.len().try_into()?;
+
+    // 1. The hash
+    let mut sign_info = EU_SIGN_INFO::default();
+
+    let err = unsafe {
+        EUVerifyHash(
+            ptr::null_mut(),
+            hash.as_mut_ptr(),
+            hash.len().try_into()?,
+            ptr::null_mut(),
+            signature.as_mut_ptr(),
+            signature_len,
+            &mut sign_info,
+        )
+    };
+    if err != EU_ERROR_NONE as c_ulong {
+        let err = EUSignError(err);
+        warn!(code = err.0, msg = %err.internal_message(), "the signature doesn't match the hash");
+
+        return Err(ServerError::BadRequest(
+            if err.0 == EU_ERROR_BAD_SIGNATURE as c_ulong {
+                "the signature is not over the document".into()
+            } else {
+                err.public_message()
+            },
+        ));
+    }
+
+    let time = sign_info.Time;
+    let signed_at = if sign_info.bTimeAvail != 0 {
+        to_utc(time)
+    } else {
+        None
+    };
+    let time_stamped = sign_info.bTimeStamp != 0;
+
+    unsafe { EUFreeSignInfo(&mut sign_info) };
+
+    // 2. The certificate
+    let mut cert_info: PEU_CERT_INFO_EX = ptr::null_mut();
+    let mut cert = ptr::null_mut();
+    let mut cert_len = 0;
+
+    let err = unsafe {
+        EUGetSignerInfo(
+            0,
+            ptr::null_mut(),
+            signature.as_mut_ptr(),
+            signature_len,
+            &mut cert_info,
+            &mut cert,
+            &mut cert_len,
+        )
+    };
+    if err != EU_ERROR_NONE as c_ulong {
+        return Err(EUSignError(err).into());
+    }
+
+    let (signer_full_name, signer_tax_number, issuer, serial) = unsafe {
+        let info = &*cert_info;
+        (
+            from_c_str(info.pszSubjFullName),
+            from_c_str(info.pszSubjDRFOCode),
+            from_c_str(info.pszIssuerCN),
+            from_c_str(info.pszSerial),
+        )
+    };
+
+    let err = unsafe { EUCheckCertificateByOCSP(cert, cert_len) };
+
+    unsafe {
+        EUFreeCertificateInfoEx(cert_info);
+        EUFreeMemory(cert);
+    }
+
+    if err != EU_ERROR_NONE as c_ulong {
+        let err = EUSignError(err);
+        warn!(code = err.0, msg = %err.internal_message(), %issuer, %serial, "the certificate of the signer is not good");
+
+        return Err(match err.0 as u32 {
+            EU_ERROR_CERT_BAD_BY_OCSP => {
+                ServerError::BadRequest("the certificate of the signer is revoked".into())
+            }
+            EU_ERROR_INVALID_CERT_TIME => {
+                ServerError::BadRequest("the certificate of the signer is expired".into())
+            }
+            _ => err.into(),
+        });
+    }
+
+    // 3. The signer
+    check_signer(&signer_full_name, &signer_tax_number, party)?;
+
+    Ok(SignatureReport {
+        file_hash: STANDARD.encode(&hash),
+        signer_full_name,
+        signer_tax_number,
+        issuer,
+        serial,
+        signed_at,
+        time_stamped,
+        verified_at: Utc::now(),
+    })
+}
+
+/// Checks that the certificate with this name and tax number is issued to `party`.
+///
+/// A missing tax number on either side never matches, even the other missing one.
+pub fn check_signer(
+    signer_full_name: &str,
+    signer_tax_number: &str,
+    party: &DocumentUnit,
+) -> Result<(), ServerError> {
+    let passport = &party.internal_passport;
+    let (signer_tax_number, party_tax_number) =
+        (signer_tax_number.trim(), passport.taxpayer_number.trim());
+
+    if signer_tax_number.is_empty() {
+        return Err(ServerError::BadRequest(
+            "the certificate of the signer has no tax number".into(),
+        ));
+    }
+    if party_tax_number.is_empty() {
+        return Err(ServerError::BadRequest(
+            "the documents of the party have no tax number".into(),
+        ));
+    }
+    if signer_tax_number != party_tax_number {
+        return Err(ServerError::BadRequest(
+            "the tax number of the signer is not the one of the party".into(),
+        ));
+    }
+
+    let party_full_name = format!(
+        "{} {} {}",
+        passport.last_name_ua, passport.first_name_ua, passport.middle_name_ua
+    );
+    if normalize_name(signer_full_name) != normalize_name(&party_full_name) {
+        return Err(ServerError::BadRequest(
+            "the name of the signer is not the one of the party".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Copies a string owned by the library, an empty one if there is none.
+unsafe fn from_c_str(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+}
+
+/// Names are compared regardless of the case, spacing and the kind of apostrophe.
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(['’', 'ʼ', '`'], "'")
+        .to_lowercase()
+}
+
+/// The library reports the times in UTC, see `Initialize`.
+fn to_utc(time: SYSTEMTIME) -> Option<DateTime<Utc>> {
+    NaiveDate::from_ymd_opt(time.wYear.into(), time.wMonth.into(), time.wDay.into())?
+        .and_hms_milli_opt(
+            time.wHour.into(),
+            time.wMinute.into(),
+            time.wSecond.into(),
+            time.wMilliseconds.into(),
+        )
+        .map(|time| time.and_utc())
+}
+
 pub fn read_file_to_base64(path: &str) -> Result<String, ServerError> {
     let mut file = File::open(path)?;
 
@@ -0,0 +1,62 @@
+use chrono::Utc;
+use tracing::{error, info};
+
+use super::{
+    config::NotificationsConfig,
+    db::{self, AgreementAction, DbPool, EndedAgreement},
+    notifications::{notify, Notification},
+    server_error::ServerError,
+};
+
+/// Moves the signed agreements that reached their end to `expired`,
+/// or to `terminated` if they were ended early by a termination act.
+///
+/// Every moved agreement is notified about. A failure to move one agreement
+/// doesn't stop the others, it's retried on the next run.
+pub async fn expire_agreements(
+    pool: &DbPool,
+    notifications: &NotificationsConfig,
+) -> Result<(), ServerError> {
+    for EndedAgreement {
+        key: agreement,
+        terminated,
+    } in db::get_ended_agreements(pool, Utc::now().naive_utc()).await?
+    {
+        let action = if terminated {
+            AgreementAction::Terminate
+        } else {
+            AgreementAction::Expire
+        };
+
+        if let Err(e) = db::apply_transition_on(
+            pool,
+            agreement.tenant_id,
+            agreement.landlord_id,
+            agreement.housing_id,
+            agreement.date,
+            action,
+        )
+        .await
+        {
+            error!("couldn't end the agreement {}: {e:?}", agreement.id);
+            continue;
+        }
+
+        info!(
+            "Agreement {} is {}",
+            agreement.id,
+            if terminated { "terminated" } else { "expired" }
+        );
+
+        notify(
+            notifications,
+            if terminated {
+                Notification::AgreementTerminated { agreement }
+            } else {
+                Notification::AgreementExpired { agreement }
+            },
+        );
+    }
+
+    Ok(())
+}
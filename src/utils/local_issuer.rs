@@ -0,0 +1,183 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{
+    encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse,
+    },
+    Algorithm, EncodingKey, Header,
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use uuid::Uuid;
+
+use super::server_error::ServerError;
+
+/// The `iss` of the tokens minted by the [`LocalIssuer`].
+pub const LOCAL_ISSUER: &str = "kaze-local-issuer";
+
+/// The `aud` of the tokens minted by the [`LocalIssuer`].
+pub const LOCAL_AUDIENCE: &str = "kaze-backend";
+
+pub const DEFAULT_KEY_PATH: &str = "./local_issuer_key.pem";
+
+const PEM_LABEL: &str = "PRIVATE KEY";
+
+/// Signs tokens for any user with a P-256 key kept on disk.
+///
+/// The server verifies them with the public half, as it would verify the tokens of
+/// a real provider, while the `test-token` command mints them for testing.
+pub struct LocalIssuer {
+    pkcs8: Vec<u8>,
+    public_key: Vec<u8>,
+    kid: String,
+}
+
+#[derive(Serialize)]
+struct Claims {
+    sub: String,
+    iss: &'static str,
+    aud: &'static str,
+    iat: i64,
+    exp: i64,
+}
+
+impl LocalIssuer {
+    /// Reads the key at `path`, generating it on the first use.
+    pub fn load_or_generate(path: &str) -> Result<Self, ServerError> {
+        let pkcs8 = match fs::read_to_string(path) {
+            Ok(pem) => {
+                from_pem(&pem).with_context(|| format!("Failed to read the key at {path}"))?
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+                    &ECDSA_P256_SHA256_FIXED_SIGNING,
+                    &SystemRandom::new(),
+                )
+                .map_err(|_| anyhow!("Failed to generate the key of the local issuer"))?;
+
+                if let Some(dir) = Path::new(path).parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                // readable by the owner only, and never replacing the key of a concurrent start
+                let file = fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .mode(0o600)
+                    .open(path);
+                let mut file = match file {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                        return Self::load_or_generate(path)
+                    }
+                    Err(e) => {
+                        Err(e).with_context(|| format!("Failed to create the key at {path}"))?
+                    }
+                };
+                file.write_all(to_pem(pkcs8.as_ref()).as_bytes())
+                    .with_context(|| format!("Failed to write the key to {path}"))?;
+                info!("Generated the key of the local issuer at {path}.");
+
+                pkcs8.as_ref().to_vec()
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read the key at {path}"))?,
+        };
+
+        let key_pair = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            &pkcs8,
+            &SystemRandom::new(),
+        )
+        .map_err(|e| anyhow!("The key at {path} is not a P-256 key: {e}"))?;
+        let public_key = key_pair.public_key().as_ref().to_vec();
+        let kid = URL_SAFE_NO_PAD.encode(&Sha256::digest(&public_key)[..16]);
+
+        Ok(Self {
+            pkcs8,
+            public_key,
+            kid,
+        })
+    }
+
+    /// The public key, for the server to verify the tokens with.
+    pub fn jwks(&self) -> JwkSet {
+        // an uncompressed point: 0x04 || x || y
+        let (x, y) = self.public_key[1..].split_at(32);
+
+        JwkSet {
+            keys: vec![Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: Some(KeyAlgorithm::ES256),
+                    key_id: Some(self.kid.clone()),
+                    ..Default::default()
+                },
+                algorithm: AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                }),
+            }],
+        }
+    }
+
+    /// Returns a token issued to `uid` that expires after `ttl`.
+    pub fn mint(&self, uid: Uuid, ttl: Duration) -> Result<String, ServerError> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: uid.to_string(),
+            iss: LOCAL_ISSUER,
+            aud: LOCAL_AUDIENCE,
+            iat: now,
+            exp: now + ttl.num_seconds(),
+        };
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.kid.clone());
+
+        Ok(
+            encode(&header, &claims, &EncodingKey::from_ec_der(&self.pkcs8))
+                .context("Failed to sign the token")?,
+        )
+    }
+}
+
+fn to_pem(der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let lines = encoded
+        .as_bytes()
+        .chunks(64)
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("-----BEGIN {PEM_LABEL}-----\n{lines}\n-----END {PEM_LABEL}-----\n")
+}
+
+fn from_pem(pem: &str) -> anyhow::Result<Vec<u8>> {
+    let body = pem
+        .trim()
+        .strip_prefix(&format!("-----BEGIN {PEM_LABEL}-----"))
+        .and_then(|rest| rest.strip_suffix(&format!("-----END {PEM_LABEL}-----")))
+        .ok_or_else(|| anyhow!("not a PEM encoded private key"))?;
+
+    Ok(STANDARD.decode(body.split_whitespace().collect::<String>())?)
+}
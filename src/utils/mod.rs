@@ -1,11 +1,20 @@
 pub mod agreement;
+pub mod agreements;
+pub mod amendments;
+pub mod auth;
+pub mod cache;
 pub mod config;
 pub mod db;
 pub mod diia;
 pub mod eusign;
+pub mod expiry;
+#[cfg(feature = "dev")]
+pub mod local_issuer;
+pub mod notifications;
 pub mod s3;
 pub mod secrets;
 pub mod server_error;
 pub mod shutdown;
+pub mod templates;
 pub mod typst;
 pub mod verify_jwt;
@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::{config::NotificationsConfig, db::AgreementKey};
+
+/// Something the parties of an agreement should be told about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    /// The agreement is past its end date.
+    AgreementExpired {
+        #[serde(flatten)]
+        agreement: AgreementKey,
+    },
+    /// One of the parties asks to terminate the agreement before its end date.
+    TerminationProposed {
+        #[serde(flatten)]
+        agreement: AgreementKey,
+        /// The number of the termination act among the amendments of the agreement.
+        number: i32,
+        by: Uuid,
+        end: NaiveDateTime,
+    },
+    /// The agreement was terminated by a termination act signed by both parties.
+    AgreementTerminated {
+        #[serde(flatten)]
+        agreement: AgreementKey,
+    },
+}
+
+/// Posts the notification to every configured webhook.
+///
+/// Delivery happens in the background, failures are only logged,
+/// so that a broken hook never fails the action it's notified about.
+pub fn notify(config: &NotificationsConfig, notification: Notification) {
+    info!("Notification: {notification:?}");
+
+    for webhook in config.webhooks.clone() {
+        let notification = notification.clone();
+        tokio::spawn(async move {
+            let response = reqwest::Client::new()
+                .post(&webhook)
+                .json(&notification)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            if let Err(e) = response {
+                error!("couldn't deliver notification to {webhook}: {e}");
+            }
+        });
+    }
+}
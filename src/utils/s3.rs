@@ -2,106 +2,159 @@ use crate::commands::server::ServerState;
 use anyhow::anyhow;
 use uuid::Uuid;
 
-use super::server_error::ServerError;
+use super::{db::AmendmentKey, server_error::ServerError};
 
 // Uploads agreement PDF to S3
 pub async fn upload_agreement_pdf(
     state: &ServerState,
     body: Vec<u8>,
-    tenant_id: Uuid,
-    landlord_id: Uuid,
-    housing_id: Uuid,
+    agreement_id: Uuid,
 ) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
-    let key = get_key_for_s3(tenant_id, landlord_id, housing_id);
+    let key = get_key_for_s3(agreement_id);
+    put_object(state, key, body, "application/pdf").await
+}
+
+// Uploads the PDF of a new agreement to S3, never replacing an existing one
+pub async fn create_agreement_pdf(
+    state: &ServerState,
+    body: Vec<u8>,
+    agreement_id: Uuid,
+) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
+    let key = get_key_for_s3(agreement_id);
     let body = aws_sdk_s3::primitives::ByteStream::from(body);
     state
         .aws_s3_client
         .put_object()
         .bucket(&state.s3_bucket_name)
-        .key(key)
+        .key(&key)
         .body(body)
-        .content_type("application/pdf") // Add this line
+        .content_type("application/pdf")
+        .if_none_match("*")
         .send()
         .await
-        .map_err(ServerError::from)
+        .map_err(|err| {
+            // 412 means that the precondition of `if_none_match` failed
+            if err.raw_response().map(|r| r.status().as_u16()) == Some(412) {
+                ServerError::Conflict(format!("the PDF {key} is already stored"))
+            } else {
+                err.into()
+            }
+        })
 }
 
 // Uploads a signed agreement to S3
 pub async fn upload_agreement_p7s(
     state: &ServerState,
     body: Vec<u8>,
-    tenant_id: Uuid,
-    landlord_id: Uuid,
-    housing_id: Uuid,
+    agreement_id: Uuid,
 ) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
-    let key = get_signature_key_for_s3(tenant_id, landlord_id, housing_id);
-    let body = aws_sdk_s3::primitives::ByteStream::from(body);
-    state
-        .aws_s3_client
-        .put_object()
-        .bucket(&state.s3_bucket_name)
-        .key(key)
-        .body(body)
-        .content_type("application/pkcs7-signature") // Add this line
-        .send()
-        .await
-        .map_err(ServerError::from)
+    let key = get_signature_key_for_s3(agreement_id);
+    put_object(state, key, body, "application/pkcs7-signature").await
+}
+
+// Uploads amendment PDF to S3
+pub async fn upload_amendment_pdf(
+    state: &ServerState,
+    body: Vec<u8>,
+    key: &AmendmentKey,
+) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
+    put_object(
+        state,
+        get_amendment_key_for_s3(key),
+        body,
+        "application/pdf",
+    )
+    .await
+}
+
+// Uploads a signed amendment to S3
+pub async fn upload_amendment_p7s(
+    state: &ServerState,
+    body: Vec<u8>,
+    key: &AmendmentKey,
+) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
+    put_object(
+        state,
+        get_amendment_signature_key_for_s3(key),
+        body,
+        "application/pkcs7-signature",
+    )
+    .await
+}
+
+pub fn get_key_for_s3(agreement_id: Uuid) -> String {
+    agreement_id.to_string()
 }
 
-pub fn get_key_for_s3(tenant_id: Uuid, landlord_id: Uuid, housing_id: Uuid) -> String {
-    tenant_id.to_string() + "_" + &landlord_id.to_string() + "_" + &housing_id.to_string()
+pub fn get_signature_key_for_s3(agreement_id: Uuid) -> String {
+    get_key_for_s3(agreement_id) + "_signed"
 }
 
-pub fn get_signature_key_for_s3(tenant_id: Uuid, landlord_id: Uuid, housing_id: Uuid) -> String {
-    tenant_id.to_string()
-        + "_"
-        + &landlord_id.to_string()
-        + "_"
-        + &housing_id.to_string()
-        + "_signed"
+pub fn get_amendment_key_for_s3(key: &AmendmentKey) -> String {
+    get_key_for_s3(key.agreement_id) + "_amendment_" + &key.number.to_string()
+}
+
+pub fn get_amendment_signature_key_for_s3(key: &AmendmentKey) -> String {
+    get_amendment_key_for_s3(key) + "_signed"
 }
 
 // Returns a PDF from the S3 bucket
 pub async fn get_agreement_pdf(
     state: &ServerState,
-    tenant_id: Uuid,
-    landlord_id: Uuid,
-    housing_id: Uuid,
+    agreement_id: Uuid,
 ) -> Result<Vec<u8>, ServerError> {
-    let mut object = state
-        .aws_s3_client
-        .get_object()
-        .bucket(state.s3_bucket_name.clone())
-        .key(get_key_for_s3(tenant_id, landlord_id, housing_id))
-        .send()
-        .await?;
-
-    let mut result = vec![];
-
-    while let Some(bytes) = object
-        .body
-        .try_next()
-        .await
-        .map_err(|err| anyhow!("Failed to read from S3 download stream: {err:?}"))?
-    {
-        result.append(&mut bytes.to_vec());
-    }
-
-    Ok(result)
+    get_object(state, get_key_for_s3(agreement_id)).await
 }
 
 // Returns a signed PDF from the S3 bucket.
 pub async fn get_agreement_ps7(
     state: &ServerState,
-    tenant_id: Uuid,
-    landlord_id: Uuid,
-    housing_id: Uuid,
+    agreement_id: Uuid,
+) -> Result<Vec<u8>, ServerError> {
+    get_object(state, get_signature_key_for_s3(agreement_id)).await
+}
+
+// Returns an amendment PDF from the S3 bucket
+pub async fn get_amendment_pdf(
+    state: &ServerState,
+    key: &AmendmentKey,
+) -> Result<Vec<u8>, ServerError> {
+    get_object(state, get_amendment_key_for_s3(key)).await
+}
+
+// Returns a signed amendment from the S3 bucket
+pub async fn get_amendment_p7s(
+    state: &ServerState,
+    key: &AmendmentKey,
 ) -> Result<Vec<u8>, ServerError> {
+    get_object(state, get_amendment_signature_key_for_s3(key)).await
+}
+
+async fn put_object(
+    state: &ServerState,
+    key: String,
+    body: Vec<u8>,
+    content_type: &str,
+) -> Result<aws_sdk_s3::operation::put_object::PutObjectOutput, ServerError> {
+    let body = aws_sdk_s3::primitives::ByteStream::from(body);
+    state
+        .aws_s3_client
+        .put_object()
+        .bucket(&state.s3_bucket_name)
+        .key(key)
+        .body(body)
+        .content_type(content_type)
+        .send()
+        .await
+        .map_err(ServerError::from)
+}
+
+async fn get_object(state: &ServerState, key: String) -> Result<Vec<u8>, ServerError> {
     let mut object = state
         .aws_s3_client
         .get_object()
         .bucket(state.s3_bucket_name.clone())
-        .key(get_signature_key_for_s3(tenant_id, landlord_id, housing_id))
+        .key(key)
         .send()
         .await?;
 
@@ -14,6 +14,26 @@ struct ErrorResponse {
     code: &'static str,
     /// Human‑readable description that is safe to expose.
     message: String,
+    /// Typst errors, when the document can't be rendered.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<TypstDiagnostic>,
+}
+
+/// A single error or warning reported by the Typst compiler.
+#[derive(Debug, Clone, Serialize)]
+pub struct TypstDiagnostic {
+    /// Either `error` or `warning`.
+    pub severity: &'static str,
+    pub message: String,
+    /// The file the diagnostic points to, `None` for the rendered template.
+    /// Kept for the logs only.
+    #[serde(skip)]
+    pub file: Option<String>,
+    /// 1-based line of the diagnostic, if it has a location.
+    pub line: Option<usize>,
+    /// 1-based column of the diagnostic, if it has a location.
+    pub column: Option<usize>,
+    pub hints: Vec<String>,
 }
 
 /// Top‑level error type used throughout the code‑base.
@@ -26,10 +46,14 @@ pub enum ServerError {
     BadRequest(String),
     /// 401 – the caller is not authenticated or the token is invalid/expired.
     Unauthorized(String),
+    /// 403 – the caller is authenticated, but not allowed to do this.
+    Forbidden(String),
     /// 404 – the requested resource does not exist (or does not belong to the caller).
     NotFound(String),
     /// 409 – business‑logic conflict (duplicate, already exists, etc.).
     Conflict(String),
+    /// 422 – the document can't be rendered from the given data.
+    Typst(Vec<TypstDiagnostic>),
     /// Special wrapper for errors coming from the EUSignCP FFI.
     Eusign(EUSignError),
     /// 500 – any other error that we did not explicitly classify.
@@ -41,8 +65,36 @@ impl IntoResponse for ServerError {
         match self {
             ServerError::BadRequest(msg) => json(StatusCode::BAD_REQUEST, "bad_request", msg),
             ServerError::Unauthorized(msg) => json(StatusCode::UNAUTHORIZED, "unauthorized", msg),
+            ServerError::Forbidden(msg) => json(StatusCode::FORBIDDEN, "forbidden", msg),
             ServerError::NotFound(msg) => json(StatusCode::NOT_FOUND, "not_found", msg),
             ServerError::Conflict(msg) => json(StatusCode::CONFLICT, "conflict", msg),
+            ServerError::Typst(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    error!(?diagnostic, "Typst diagnostic");
+                }
+
+                // Only the errors go to the caller, without the file paths.
+                let diagnostics: Vec<_> = diagnostics
+                    .into_iter()
+                    .filter(|diagnostic| diagnostic.severity == "error")
+                    .collect();
+                let message = match diagnostics.first() {
+                    Some(diagnostic) => {
+                        format!("the document can't be rendered: {}", diagnostic.message)
+                    }
+                    None => "the document can't be rendered".to_string(),
+                };
+
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(ErrorResponse {
+                        code: "typst_error",
+                        message,
+                        diagnostics,
+                    }),
+                )
+                    .into_response()
+            }
             ServerError::Eusign(err) => {
                 // We keep only a terse public message. Full diagnostics go to the log.
                 error!(code = err.0, msg = %err.internal_message(), "EUSign error");
@@ -67,7 +119,15 @@ impl IntoResponse for ServerError {
 
 /// Helper – build a `(StatusCode, Json<ErrorResponse>)` and convert to `Response`.
 fn json(code: StatusCode, tag: &'static str, message: String) -> axum::response::Response {
-    (code, Json(ErrorResponse { code: tag, message })).into_response()
+    (
+        code,
+        Json(ErrorResponse {
+            code: tag,
+            message,
+            diagnostics: Vec::new(),
+        }),
+    )
+        .into_response()
 }
 
 //──────────────────────────────────────────────────────────────────────────────
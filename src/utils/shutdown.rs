@@ -2,11 +2,12 @@ use axum_server::Handle;
 
 use std::time::Duration;
 use tokio::signal;
-use tracing::info;
+use tracing::{error, info};
 
 use crate::{
     commands::server::ServerState,
     utils::{
+        cache::{save_cache_to_file, CACHE_SAVE_LOCATION_DEFAULT},
         eusign::EUUnload,
     },
 };
@@ -14,7 +15,7 @@ use crate::{
 /// This function is used for graceful shutdown.
 /// Probably should be replaced with something more robust.
 /// It was decided to panic in case we were unable to install a signal handler.
-pub async fn graceful_shutdown(handle: Handle, _state: ServerState) {
+pub async fn graceful_shutdown(handle: Handle, state: ServerState) {
     let ctrl_c = async {
         signal::ctrl_c()
             .await
@@ -41,6 +42,11 @@ pub async fn graceful_shutdown(handle: Handle, _state: ServerState) {
 
     handle.graceful_shutdown(Some(Duration::from_secs(10)));
 
+    // the proposals waiting for a confirmation outlive the restart
+    if let Err(e) = save_cache_to_file(CACHE_SAVE_LOCATION_DEFAULT, &state.cache).await {
+        error!("couldn't save the agreement proposals: {:?}", e);
+    }
+
     // Free the EUSign library
     unsafe {
         EUUnload();
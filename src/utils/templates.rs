@@ -0,0 +1,423 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+use typst::foundations::{Datetime, Smart};
+use typst_pdf::{PdfOptions, PdfStandard, PdfStandards, Timestamp};
+
+use super::{
+    agreement::{
+        generate, generate_amendment, generate_termination, AgreementInputs, AmendmentChanges,
+        DocumentInfo, AGREEMENT_DATA_PATH,
+    },
+    config::TypstConfig,
+    server_error::ServerError,
+    typst::{TypstAssets, TypstWrapperWorld},
+};
+
+/// A kind of agreement. Every kind is rendered from its own template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateId {
+    #[default]
+    ResidentialLease,
+    CommercialLease,
+    ShortTermRental,
+    ParkingSpace,
+    /// An amendment to an already signed agreement.
+    Amendment,
+    /// An act terminating a signed agreement before its end date.
+    Termination,
+}
+
+impl TemplateId {
+    pub const ALL: [TemplateId; 6] = [
+        TemplateId::ResidentialLease,
+        TemplateId::CommercialLease,
+        TemplateId::ShortTermRental,
+        TemplateId::ParkingSpace,
+        TemplateId::Amendment,
+        TemplateId::Termination,
+    ];
+
+    /// The name of the template, as stored in the database and on disk.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TemplateId::ResidentialLease => "residential_lease",
+            TemplateId::CommercialLease => "commercial_lease",
+            TemplateId::ShortTermRental => "short_term_rental",
+            TemplateId::ParkingSpace => "parking_space",
+            TemplateId::Amendment => "amendment",
+            TemplateId::Termination => "termination",
+        }
+    }
+
+    /// Whether the template renders an agreement itself rather than an amendment to it.
+    pub fn is_agreement(self) -> bool {
+        !matches!(self, TemplateId::Amendment | TemplateId::Termination)
+    }
+}
+
+impl fmt::Display for TemplateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TemplateId {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TemplateId::ALL
+            .into_iter()
+            .find(|id| id.as_str() == s)
+            .ok_or_else(|| ServerError::BadRequest(format!("unknown template `{s}`")))
+    }
+}
+
+/// A specific version of an agreement template.
+#[derive(Clone)]
+pub struct Template {
+    pub id: TemplateId,
+    pub version: i32,
+    /// The Typst source of the template.
+    pub source: Arc<String>,
+    /// Settings of the template version.
+    pub settings: TemplateSettings,
+    /// Fonts and files the template is rendered with.
+    pub assets: Arc<TypstAssets>,
+}
+
+/// Settings of a template version, read from `v<version>.toml` next to the template.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TemplateSettings {
+    /// The PDF standards the output must conform to, e.g. `["a-2b"]` for archiving.
+    #[serde(default)]
+    pub pdf_standards: Vec<PdfStandardName>,
+}
+
+/// A PDF standard a template can be rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum PdfStandardName {
+    #[serde(rename = "a-2b")]
+    A2b,
+    #[serde(rename = "a-3b")]
+    A3b,
+}
+
+impl PdfStandardName {
+    fn standard(self) -> PdfStandard {
+        match self {
+            PdfStandardName::A2b => PdfStandard::A_2b,
+            PdfStandardName::A3b => PdfStandard::A_3b,
+        }
+    }
+}
+
+impl Template {
+    /// Creates a world to render the template in, with `now` as the current time.
+    pub fn world(&self, now: DateTime<Utc>) -> TypstWrapperWorld {
+        TypstWrapperWorld::new(self.assets.clone(), (*self.source).clone(), now)
+    }
+
+    /// Renders the agreement, or an amendment to it, into a PDF.
+    ///
+    /// The agreement id identifies the document and the creation moment is its timestamp.
+    /// The result depends on nothing else but the data, so the same agreement is always
+    /// rendered into the same bytes, hence the same hash is signed in Diia.
+    pub fn render(
+        &self,
+        data: &impl Serialize,
+        info: &DocumentInfo,
+    ) -> Result<Vec<u8>, ServerError> {
+        let world = self
+            .world(info.created_at)
+            .with_virtual_file(AGREEMENT_DATA_PATH, serde_json::to_vec(data)?);
+        let document = world.compile()?;
+
+        let standards: Vec<_> = self
+            .settings
+            .pdf_standards
+            .iter()
+            .map(|standard| standard.standard())
+            .collect();
+        let created_at = info.created_at;
+        let timestamp = Datetime::from_ymd_hms(
+            created_at.year(),
+            created_at.month().try_into()?,
+            created_at.day().try_into()?,
+            created_at.hour().try_into()?,
+            created_at.minute().try_into()?,
+            created_at.second().try_into()?,
+        )
+        .map(Timestamp::new_utc);
+
+        let options = PdfOptions {
+            ident: Smart::Custom(&info.agreement_id),
+            timestamp,
+            standards: PdfStandards::new(&standards).map_err(|e| anyhow!("{e}"))?,
+            ..Default::default()
+        };
+
+        world.pdf(&document, &options)
+    }
+}
+
+/// Versions of each template.
+type Templates = HashMap<TemplateId, BTreeMap<i32, Template>>;
+
+/// All versions of all agreement templates.
+///
+/// The templates are read from `<dir>/<template id>/v<version>.typ`.
+/// A published version must never be edited: agreements record the
+/// template id and version they were rendered with, so that they can be
+/// re-rendered exactly as signed. Changes go into a new version instead.
+///
+/// The fonts from `./fonts` are part of every published version as well, so they can't
+/// change while any version is published. The templates can be reloaded at runtime,
+/// see [`TemplateRegistry::reload`].
+pub struct TemplateRegistry {
+    dir: String,
+    config: TypstConfig,
+    templates: RwLock<Arc<Templates>>,
+    /// Serializes reloads, so that each one is checked against the latest set.
+    reload_lock: Mutex<()>,
+}
+
+impl TemplateRegistry {
+    /// Loads and compile-checks every template version from `dir`.
+    pub async fn load(dir: &str, config: TypstConfig) -> Result<Self, ServerError> {
+        let templates = read_templates(dir, load_assets(&config).await?).await?;
+        check_templates(&templates).await?;
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            config,
+            templates: RwLock::new(Arc::new(templates)),
+            reload_lock: Mutex::new(()),
+        })
+    }
+
+    /// Re-reads the templates and fonts from disk and swaps them in.
+    ///
+    /// The latest version of every template is compiled against the fixture data first.
+    /// Already published versions must stay untouched, along with the fonts they're
+    /// rendered with. If anything fails, the current templates are kept as is.
+    pub async fn reload(&self) -> Result<(), ServerError> {
+        let _guard = self.reload_lock.lock().await;
+
+        // the current templates read the edited files from disk too, should the reload fail
+        let current = self.snapshot();
+        for template in current.values().flat_map(BTreeMap::values) {
+            template.assets.clear_local_files();
+        }
+
+        let templates = read_templates(&self.dir, load_assets(&self.config).await?).await?;
+
+        for (id, versions) in current.iter() {
+            for (version, template) in versions {
+                match templates.get(id).and_then(|versions| versions.get(version)) {
+                    Some(new)
+                        if new.source != template.source || new.settings != template.settings =>
+                    {
+                        return Err(ServerError::Conflict(format!(
+                            "template {id} v{version} was modified, publish a new version instead"
+                        )))
+                    }
+                    Some(new) if new.assets.fonts_digest() != template.assets.fonts_digest() => {
+                        return Err(ServerError::Conflict(format!(
+                            "the fonts were modified, while template {id} v{version} is rendered with them"
+                        )))
+                    }
+                    Some(_) => {}
+                    None => {
+                        return Err(ServerError::Conflict(format!(
+                            "template {id} v{version} was removed"
+                        )))
+                    }
+                }
+            }
+        }
+
+        check_templates(&templates).await?;
+
+        *self
+            .templates
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(templates);
+        info!("Agreement templates were reloaded");
+
+        Ok(())
+    }
+
+    /// Returns the latest version of the template, used for new agreements.
+    pub fn latest(&self, id: TemplateId) -> Result<Template, ServerError> {
+        self.snapshot()
+            .get(&id)
+            .and_then(|versions| versions.last_key_value())
+            .map(|(_, template)| template.clone())
+            .ok_or_else(|| ServerError::NotFound(format!("no template for {id}")))
+    }
+
+    /// Returns the exact version of the template, used to re-render existing agreements.
+    pub fn get(&self, id: TemplateId, version: i32) -> Result<Template, ServerError> {
+        self.snapshot()
+            .get(&id)
+            .and_then(|versions| versions.get(&version))
+            .cloned()
+            .ok_or_else(|| ServerError::NotFound(format!("no template {id} v{version}")))
+    }
+
+    fn snapshot(&self) -> Arc<Templates> {
+        self.templates
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// Reads the fonts once for all the templates.
+async fn load_assets(config: &TypstConfig) -> Result<Arc<TypstAssets>, ServerError> {
+    let config = config.clone();
+    let assets =
+        tokio::task::spawn_blocking(move || TypstAssets::new("./".to_owned(), &config)).await??;
+    Ok(Arc::new(assets))
+}
+
+/// Reads every template version from `dir`.
+async fn read_templates(dir: &str, assets: Arc<TypstAssets>) -> Result<Templates, ServerError> {
+    let mut templates = HashMap::new();
+
+    for id in TemplateId::ALL {
+        let path = format!("{dir}/{id}");
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(anyhow!("cannot read template dir {path}: {e}").into()),
+        };
+
+        let mut versions = BTreeMap::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some(version) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix('v'))
+                .and_then(|name| name.strip_suffix(".typ"))
+                .and_then(|version| version.parse::<i32>().ok())
+            else {
+                continue;
+            };
+
+            let source = tokio::fs::read_to_string(entry.path())
+                .await
+                .with_context(|| format!("cannot read template {}", entry.path().display()))?;
+
+            let settings_path = entry.path().with_extension("toml");
+            let settings = match tokio::fs::read_to_string(&settings_path).await {
+                Ok(settings) => toml::from_str(&settings).with_context(|| {
+                    format!("cannot parse template settings {}", settings_path.display())
+                })?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => TemplateSettings::default(),
+                Err(e) => return Err(e.into()),
+            };
+
+            versions.insert(
+                version,
+                Template {
+                    id,
+                    version,
+                    source: Arc::new(source),
+                    settings,
+                    assets: assets.clone(),
+                },
+            );
+        }
+
+        if let Some((latest, _)) = versions.last_key_value() {
+            info!(
+                "Loaded {} versions of template {id}, latest is v{latest}",
+                versions.len()
+            );
+            templates.insert(id, versions);
+        }
+    }
+
+    if templates.is_empty() {
+        return Err(anyhow!("no agreement templates found in {dir}").into());
+    }
+
+    Ok(templates)
+}
+
+/// Renders the latest version of every template from the fixture data.
+async fn check_templates(templates: &Templates) -> Result<(), ServerError> {
+    let latest: Vec<Template> = templates
+        .values()
+        .filter_map(|versions| versions.last_key_value())
+        .map(|(_, template)| template.clone())
+        .collect();
+
+    tokio::task::spawn_blocking(move || -> Result<(), ServerError> {
+        for template in latest {
+            let info = DocumentInfo {
+                agreement_id: "fixture".into(),
+                template_id: template.id,
+                template_version: template.version,
+                created_at: Utc::now(),
+            };
+            template
+                .render(&fixture_data(&info)?, &info)
+                .inspect_err(|_| {
+                    error!(
+                        "Template {} v{} can't be rendered",
+                        template.id, template.version
+                    )
+                })?;
+        }
+
+        Ok(())
+    })
+    .await?
+}
+
+/// The data every template must be able to render.
+fn fixture_data(info: &DocumentInfo) -> Result<serde_json::Value, ServerError> {
+    let inputs = AgreementInputs::default();
+
+    let changes = AmendmentChanges {
+        price: Some(1),
+        end: Some(info.created_at.naive_utc()),
+        additional_tenants: Some(vec!["Fixture".into()]),
+        allowed_animals: Some(vec!["Fixture".into()]),
+    };
+
+    let data = match info.template_id {
+        TemplateId::Amendment => serde_json::to_value(generate_amendment(
+            &inputs,
+            info.created_at,
+            1,
+            &changes,
+            info,
+        )?)?,
+        TemplateId::Termination => serde_json::to_value(generate_termination(
+            &inputs,
+            info.created_at,
+            1,
+            &changes,
+            info,
+        )?)?,
+        TemplateId::ResidentialLease
+        | TemplateId::CommercialLease
+        | TemplateId::ShortTermRental
+        | TemplateId::ParkingSpace => serde_json::to_value(generate(inputs, 1, info)?)?,
+    };
+    Ok(data)
+}
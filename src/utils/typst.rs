@@ -1,65 +1,203 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-
-use tracing::info;
-use typst::diag::{eco_format, FileError, FileResult, PackageError, PackageResult};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use typst::diag::{
+    eco_format, FileError, FileResult, PackageError, PackageResult, Severity, SourceDiagnostic,
+    Warned,
+};
 use typst::foundations::{Bytes, Datetime};
+use typst::layout::PagedDocument;
 use typst::syntax::package::PackageSpec;
-use typst::syntax::{FileId, Source};
+use typst::syntax::{FileId, Source, VirtualPath};
 use typst::text::{Font, FontBook};
 use typst::utils::LazyHash;
-use typst::Library;
+use typst::{Library, World};
+use typst_pdf::PdfOptions;
 
-/// Main interface that determines the environment for Typst.
-pub struct TypstWrapperWorld {
+use super::config::TypstConfig;
+use super::server_error::{ServerError, TypstDiagnostic};
+
+/// Everything that can be shared by the worlds: fonts, the standard library
+/// and the files read from disk or downloaded packages.
+///
+/// Building it reads and parses every font, so it's done once and shared
+/// between the requests.
+pub struct TypstAssets {
     /// Root path to which files will be resolved.
     root: PathBuf,
 
-    /// The content of a source.
-    source: Source,
-
     /// The standard library.
     library: LazyHash<Library>,
 
     /// Metadata about all known fonts.
     book: LazyHash<FontBook>,
 
-    /// Metadata about all known fonts.
+    /// All known fonts.
     fonts: Vec<Font>,
 
-    /// Map of all known files.
-    files: Arc<Mutex<HashMap<FileId, FileEntry>>>,
+    /// The SHA-256 of the font files, see [`TypstAssets::fonts_digest`].
+    fonts_digest: Vec<u8>,
+
+    /// Map of all files read from disk or packages.
+    files: Mutex<HashMap<FileId, FileEntry>>,
+
+    /// A lock per package, so that each package is downloaded once.
+    downloads: Mutex<HashMap<PackageSpec, Arc<Mutex<()>>>>,
 
     /// Cache directory (e.g. where packages are downloaded to).
     cache_directory: PathBuf,
 
+    /// Directory with the vendored packages.
+    packages_dir: Option<PathBuf>,
+
+    /// Whether the packages that aren't vendored can be downloaded.
+    offline: bool,
+
     /// http agent to download packages.
     http: ureq::Agent,
-
-    /// Datetime.
-    time: time::OffsetDateTime,
 }
 
-impl TypstWrapperWorld {
-    pub fn new(root: String, source: String) -> Self {
+impl TypstAssets {
+    /// Loads the fonts from `<root>/fonts`.
+    pub fn new(root: String, config: &TypstConfig) -> anyhow::Result<Self> {
         let root = PathBuf::from(root);
-        let fonts = fonts(&root);
+        let (fonts, fonts_digest) = fonts(&root)?;
 
-        Self {
+        Ok(Self {
             library: LazyHash::new(Library::default()),
             book: LazyHash::new(FontBook::from_fonts(&fonts)),
             root,
             fonts,
-            source: Source::detached(source),
-            time: time::OffsetDateTime::now_utc(),
+            fonts_digest,
             cache_directory: std::env::var_os("CACHE_DIRECTORY")
                 .map(|os_path| os_path.into())
                 .unwrap_or(std::env::temp_dir()),
+            packages_dir: config.packages_dir.as_ref().map(PathBuf::from),
+            offline: config.offline,
             http: ureq::Agent::new_with_defaults(),
-            files: Arc::new(Mutex::new(HashMap::new())),
+            files: Mutex::new(HashMap::new()),
+            downloads: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl TypstAssets {
+    /// The SHA-256 over the names and contents of the font files.
+    ///
+    /// The fonts are part of every rendered agreement, so a template version
+    /// renders into the same bytes only as long as this stays the same.
+    pub fn fonts_digest(&self) -> &[u8] {
+        &self.fonts_digest
+    }
+
+    /// Drops the local files read so far, so that they're read from disk again.
+    ///
+    /// The packages are kept, a package version never changes.
+    pub fn clear_local_files(&self) {
+        self.files
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|id, _| id.package().is_some());
+    }
+}
+
+/// Main interface that determines the environment for Typst.
+pub struct TypstWrapperWorld {
+    /// Fonts, library and files shared with other worlds.
+    assets: Arc<TypstAssets>,
+
+    /// The content of a source.
+    source: Source,
+
+    /// Files that only exist in memory of this world.
+    virtual_files: HashMap<FileId, FileEntry>,
+
+    /// Datetime.
+    time: time::OffsetDateTime,
+}
+
+impl TypstWrapperWorld {
+    /// Creates a world where `now` is the current time, as seen by the document.
+    pub fn new(assets: Arc<TypstAssets>, source: String, now: DateTime<Utc>) -> Self {
+        Self {
+            assets,
+            source: Source::detached(source),
+            time: time::OffsetDateTime::from_unix_timestamp(now.timestamp())
+                .unwrap_or(time::OffsetDateTime::UNIX_EPOCH),
+            virtual_files: HashMap::new(),
+        }
+    }
+
+    /// Adds a file that only exists in memory, e.g. the data of the agreement.
+    ///
+    /// The file is resolved against the root, like any other local file.
+    pub fn with_virtual_file(mut self, path: &str, bytes: Vec<u8>) -> Self {
+        let id = FileId::new(None, VirtualPath::new(path));
+        self.virtual_files.insert(id, FileEntry::new(bytes, None));
+        self
+    }
+}
+
+impl TypstWrapperWorld {
+    /// Compiles the document, reporting the diagnostics as [`ServerError::Typst`].
+    pub fn compile(&self) -> Result<PagedDocument, ServerError> {
+        let Warned { output, warnings } = typst::compile(self);
+
+        for warning in self.diagnostics(&warnings) {
+            warn!(?warning, "Typst warning");
         }
+
+        output.map_err(|errors| ServerError::Typst(self.diagnostics(&errors)))
+    }
+
+    /// Exports the compiled document to PDF.
+    pub fn pdf(
+        &self,
+        document: &PagedDocument,
+        options: &PdfOptions,
+    ) -> Result<Vec<u8>, ServerError> {
+        typst_pdf::pdf(document, options)
+            .map_err(|errors| ServerError::Typst(self.diagnostics(&errors)))
+    }
+
+    /// Resolves the spans of the diagnostics to lines and columns.
+    fn diagnostics(&self, diagnostics: &[SourceDiagnostic]) -> Vec<TypstDiagnostic> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let id = diagnostic.span.id();
+                let position = id
+                    .and_then(|id| World::source(self, id).ok())
+                    .and_then(|source| {
+                        let start = source.range(diagnostic.span)?.start;
+                        Some((source.byte_to_line(start)?, source.byte_to_column(start)?))
+                    });
+
+                TypstDiagnostic {
+                    severity: match diagnostic.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    },
+                    message: diagnostic.message.to_string(),
+                    file: id
+                        .filter(|id| *id != self.source.id())
+                        .map(|id| format!("{:?}", id.vpath())),
+                    line: position.map(|(line, _)| line + 1),
+                    column: position.map(|(_, column)| column + 1),
+                    hints: diagnostic
+                        .hints
+                        .iter()
+                        .map(|hint| hint.to_string())
+                        .collect(),
+                }
+            })
+            .collect()
     }
 }
 
@@ -91,96 +229,172 @@ impl FileEntry {
     }
 }
 
-impl TypstWrapperWorld {
+impl TypstAssets {
     /// Helper to handle file requests.
     ///
     /// Requests will be either in packages or a local file.
-    fn file(&self, id: FileId) -> FileResult<FileEntry> {
+    /// The file is read once and then kept for all the worlds. The lock is held only
+    /// to look the file up and to store it, so that a slow read or download doesn't
+    /// hold up the other renders.
+    fn with_file<T>(
+        &self,
+        id: FileId,
+        f: impl FnOnce(&mut FileEntry) -> FileResult<T>,
+    ) -> FileResult<T> {
+        let cached = self
+            .files
+            .lock()
+            .map_err(|_| FileError::AccessDenied)?
+            .get(&id)
+            .cloned();
+
+        let mut entry = match cached {
+            Some(entry) => entry,
+            None => {
+                let path = if let Some(package) = id.package() {
+                    // Fetching file from package
+                    let package_dir = self.package_dir(package)?;
+                    id.vpath().resolve(&package_dir)
+                } else {
+                    // Fetching file from disk
+                    id.vpath().resolve(&self.root)
+                }
+                .ok_or(FileError::AccessDenied)?;
+
+                let content =
+                    std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
+                FileEntry::new(content, None)
+            }
+        };
+
+        let result = f(&mut entry);
+
+        // keeping the parsed source too, unless another world already did
         let mut files = self.files.lock().map_err(|_| FileError::AccessDenied)?;
-        if let Some(entry) = files.get(&id) {
-            return Ok(entry.clone());
-        }
-        let path = if let Some(package) = id.package() {
-            // Fetching file from package
-            let package_dir = self.download_package(package)?;
-            id.vpath().resolve(&package_dir)
-        } else {
-            // Fetching file from disk
-            id.vpath().resolve(&self.root)
+        match files.entry(id) {
+            Entry::Occupied(mut stored) if stored.get().source.is_none() => {
+                stored.insert(entry);
+            }
+            Entry::Occupied(_) => {}
+            Entry::Vacant(stored) => {
+                stored.insert(entry);
+            }
         }
-        .ok_or(FileError::AccessDenied)?;
 
-        let content = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
-        Ok(files
-            .entry(id)
-            .or_insert(FileEntry::new(content, None))
-            .clone())
+        result
     }
 
-    /// Downloads the package and returns the system path of the unpacked package.
-    fn download_package(&self, package: &PackageSpec) -> PackageResult<PathBuf> {
-        let package_subdir = format!("{}/{}/{}", package.namespace, package.name, package.version);
+    /// Returns the system path of the unpacked package.
+    ///
+    /// Vendored packages are preferred. Other packages are downloaded into
+    /// the cache directory, unless the downloads are disabled.
+    fn package_dir(&self, package: &PackageSpec) -> PackageResult<PathBuf> {
+        let package_subdir = package_subdir(package);
+
+        if let Some(packages_dir) = &self.packages_dir {
+            let path = packages_dir.join(&package_subdir);
+            if path.exists() {
+                return Ok(path);
+            }
+        }
+
+        if self.offline {
+            return Err(PackageError::Other(Some(eco_format!(
+                "package {package} is not vendored and downloads are disabled"
+            ))));
+        }
+
         let path = self.cache_directory.join(package_subdir);
 
+        // the package is checked under its lock, so that a package
+        // being unpacked by another world isn't taken for a complete one
+        let download = self
+            .downloads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(package.clone())
+            .or_default()
+            .clone();
+        let _download = download.lock().unwrap_or_else(PoisonError::into_inner);
+
         if path.exists() {
             return Ok(path);
         }
 
-        info!("downloading {package}");
-        let url = format!(
-            "https://packages.typst.org/{}/{}-{}.tar.gz",
-            package.namespace, package.name, package.version,
-        );
-
-        let response = retry(|| {
-            let response = self
-                .http
-                .get(&url)
-                .call()
-                .map_err(|error| eco_format!("{error}"))?;
-
-            let status = response.status();
-            if !http_successful(status.into()) {
-                return Err(eco_format!(
-                    "response returned unsuccessful status code {status}",
-                ));
-            }
-
-            Ok(response)
-        })
-        .map_err(|error| PackageError::NetworkFailed(Some(error)))?;
-
-        let mut compressed_archive = Vec::new();
-        response
-            .into_body()
-            .into_reader()
-            .read_to_end(&mut compressed_archive)
-            .map_err(|error| PackageError::NetworkFailed(Some(eco_format!("{error}"))))?;
-        let raw_archive = zune_inflate::DeflateDecoder::new(&compressed_archive)
-            .decode_gzip()
-            .map_err(|error| PackageError::MalformedArchive(Some(eco_format!("{error}"))))?;
-        let mut archive = tar::Archive::new(raw_archive.as_slice());
-        archive.unpack(&path).map_err(|error| {
-            _ = std::fs::remove_dir_all(&path);
-            PackageError::MalformedArchive(Some(eco_format!("{error}")))
-        })?;
+        let compressed_archive = download_package_archive(&self.http, package)?;
+        unpack_package_archive(&compressed_archive, &path)?;
 
         Ok(path)
     }
 }
 
+/// The path of the unpacked package, relative to the packages directory.
+pub fn package_subdir(package: &PackageSpec) -> String {
+    format!("{}/{}/{}", package.namespace, package.name, package.version)
+}
+
+/// Downloads the `.tar.gz` archive of the package from the Typst registry.
+pub fn download_package_archive(
+    http: &ureq::Agent,
+    package: &PackageSpec,
+) -> PackageResult<Vec<u8>> {
+    info!("downloading {package}");
+    let url = format!(
+        "https://packages.typst.org/{}/{}-{}.tar.gz",
+        package.namespace, package.name, package.version,
+    );
+
+    let response = retry(|| {
+        let response = http
+            .get(&url)
+            .call()
+            .map_err(|error| eco_format!("{error}"))?;
+
+        let status = response.status();
+        if !http_successful(status.into()) {
+            return Err(eco_format!(
+                "response returned unsuccessful status code {status}",
+            ));
+        }
+
+        Ok(response)
+    })
+    .map_err(|error| PackageError::NetworkFailed(Some(error)))?;
+
+    let mut compressed_archive = Vec::new();
+    response
+        .into_body()
+        .into_reader()
+        .read_to_end(&mut compressed_archive)
+        .map_err(|error| PackageError::NetworkFailed(Some(eco_format!("{error}"))))?;
+
+    Ok(compressed_archive)
+}
+
+/// Unpacks the `.tar.gz` archive of the package into `path`.
+pub fn unpack_package_archive(compressed_archive: &[u8], path: &Path) -> PackageResult<()> {
+    let raw_archive = zune_inflate::DeflateDecoder::new(compressed_archive)
+        .decode_gzip()
+        .map_err(|error| PackageError::MalformedArchive(Some(eco_format!("{error}"))))?;
+    let mut archive = tar::Archive::new(raw_archive.as_slice());
+    archive.unpack(path).map_err(|error| {
+        _ = std::fs::remove_dir_all(path);
+        PackageError::MalformedArchive(Some(eco_format!("{error}")))
+    })
+}
+
 /// This is the interface we have to implement such that `typst` can compile it.
 ///
 /// I have tried to keep it as minimal as possible
 impl typst::World for TypstWrapperWorld {
     /// Standard library.
     fn library(&self) -> &LazyHash<Library> {
-        &self.library
+        &self.assets.library
     }
 
     /// Metadata about all known Books.
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.book
+        &self.assets.book
     }
 
     /// Accessing the main source file.
@@ -192,19 +406,25 @@ impl typst::World for TypstWrapperWorld {
     fn source(&self, id: FileId) -> FileResult<Source> {
         if id == self.source.id() {
             Ok(self.source.clone())
+        } else if let Some(entry) = self.virtual_files.get(&id) {
+            entry.clone().source(id)
         } else {
-            self.file(id)?.source(id)
+            self.assets.with_file(id, |entry| entry.source(id))
         }
     }
 
     /// Accessing a specified file (non-file).
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.file(id).map(|file| file.bytes.clone())
+        if let Some(entry) = self.virtual_files.get(&id) {
+            Ok(entry.bytes.clone())
+        } else {
+            self.assets.with_file(id, |entry| Ok(entry.bytes.clone()))
+        }
     }
 
     /// Accessing a specified font per index of font book.
     fn font(&self, id: usize) -> Option<Font> {
-        self.fonts.get(id).cloned()
+        self.assets.fonts.get(id).cloned()
     }
 
     /// Get the current date.
@@ -218,23 +438,38 @@ impl typst::World for TypstWrapperWorld {
     }
 }
 
-/// Helper function
-fn fonts(root: &Path) -> Vec<Font> {
-    std::fs::read_dir(root.join("fonts"))
-        .expect("Could not read fonts from disk")
-        .map(Result::unwrap)
-        .flat_map(|entry| {
-            let path = entry.path();
-            let bytes = std::fs::read(&path).unwrap();
-            let buffer = Bytes::new(bytes);
-            let face_count = ttf_parser::fonts_in_collection(&buffer).unwrap_or(1);
-            (0..face_count).map(move |face| {
-                Font::new(buffer.clone(), face).unwrap_or_else(|| {
-                    panic!("failed to load font from {path:?} (face index {face})")
-                })
-            })
-        })
-        .collect()
+/// Loads the fonts from `<root>/fonts`, in the order of their file names,
+/// and returns them along with their digest. The files that aren't fonts are skipped.
+fn fonts(root: &Path) -> anyhow::Result<(Vec<Font>, Vec<u8>)> {
+    let mut paths = std::fs::read_dir(root.join("fonts"))
+        .context("could not read fonts from disk")?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut fonts = Vec::new();
+    let mut digest = Sha256::new();
+
+    for path in paths {
+        let buffer = Bytes::new(std::fs::read(&path)?);
+        let face_count = ttf_parser::fonts_in_collection(&buffer).unwrap_or(1);
+        let faces: Vec<Font> = (0..face_count)
+            .filter_map(|face| Font::new(buffer.clone(), face))
+            .collect();
+
+        // a stray file next to the fonts doesn't keep the templates from loading
+        if faces.is_empty() {
+            warn!(?path, "skipping a file that isn't a font");
+            continue;
+        }
+
+        digest.update(path.file_name().unwrap_or_default().as_encoded_bytes());
+        digest.update((buffer.len() as u64).to_be_bytes());
+        digest.update(&buffer[..]);
+        fonts.extend(faces);
+    }
+
+    Ok((fonts, digest.finalize().to_vec()))
 }
 
 fn retry<T, E>(mut f: impl FnMut() -> Result<T, E>) -> Result<T, E> {
@@ -1,28 +1,333 @@
-use anyhow::anyhow;
-use rs_firebase_admin_sdk::auth::token::TokenVerifier;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
+use jsonwebtoken::{
+    decode, decode_header,
+    errors::ErrorKind,
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm},
+    Algorithm, DecodingKey, Validation,
+};
+use rs_firebase_admin_sdk::auth::token::{
+    cache::HttpCache, crypto::JwtRsaPubKey, LiveTokenVerifier, TokenVerifier as _,
+};
+use rs_firebase_admin_sdk::{credentials_provider, App};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
 use tracing::*;
-use uuid::Uuid;
+use uuid::{Builder, Uuid};
+
+use super::{config::AuthConfig, server_error::ServerError};
+
+/// How long the keys of a JWKS are used before they are fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A token signed with an unknown key makes the keys be fetched again,
+/// but not more often than this, so that such tokens can't flood the issuer.
+const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+pub type FirebaseTokenVerifier =
+    LiveTokenVerifier<HttpCache<reqwest::Client, BTreeMap<String, JwtRsaPubKey>>>;
+
+/// Verifies the tokens the users authenticate with, as set by [`AuthConfig`].
+pub enum TokenVerifier {
+    /// Firebase ID tokens, verified by the Firebase Admin SDK.
+    Firebase(FirebaseTokenVerifier),
+    /// Tokens signed with the keys of a JWKS, e.g. the one of a Cognito user pool.
+    Jwks(Box<JwksVerifier>),
+}
+
+impl TokenVerifier {
+    pub async fn new(config: &AuthConfig) -> anyhow::Result<Self> {
+        Ok(match config {
+            AuthConfig::Firebase => {
+                let gcp_service_account = credentials_provider()
+                    .await
+                    .map_err(|e| anyhow!("cannot receive google service account: {e:?}"))?;
+                let live_app = App::live(gcp_service_account)
+                    .await
+                    .map_err(|e| anyhow!("cannot receive google live app: {e:?}"))?;
 
-use crate::commands::server::ServerState;
+                TokenVerifier::Firebase(
+                    live_app
+                        .id_token_verifier()
+                        .await
+                        .map_err(|e| anyhow!("cannot receive google live token verifier: {e:?}"))?,
+                )
+            }
+            AuthConfig::Cognito {
+                region,
+                user_pool_id,
+                client_id,
+            } => {
+                let issuer = format!("https://cognito-idp.{region}.amazonaws.com/{user_pool_id}");
 
-use super::server_error::ServerError;
+                TokenVerifier::Jwks(Box::new(JwksVerifier::new(
+                    format!("{issuer}/.well-known/jwks.json"),
+                    &issuer,
+                    client_id,
+                )))
+            }
+            AuthConfig::Jwks {
+                jwks_url,
+                issuer,
+                audience,
+            } => TokenVerifier::Jwks(Box::new(JwksVerifier::new(
+                jwks_url.clone(),
+                issuer,
+                audience,
+            ))),
+            #[cfg(feature = "dev")]
+            AuthConfig::Local { key_path } => {
+                use super::local_issuer::{LocalIssuer, LOCAL_AUDIENCE, LOCAL_ISSUER};
 
-async fn verify_token<T: TokenVerifier>(token: &str, verifier: &T) -> Result<String, ServerError> {
-    match verifier.verify_token(token).await {
-        Ok(token) => {
-            let user_id = token.critical_claims.sub;
-            info!("Token for user {user_id} is valid!");
-            Ok(user_id)
+                warn!("Accepting the tokens of the local issuer, anyone with {key_path} can act as any user!");
+                let issuer = LocalIssuer::load_or_generate(key_path)
+                    .map_err(|e| anyhow!("cannot load the key of the local issuer: {e:?}"))?;
+
+                TokenVerifier::Jwks(Box::new(JwksVerifier::with_keys(
+                    issuer.jwks(),
+                    LOCAL_ISSUER,
+                    LOCAL_AUDIENCE,
+                )))
+            }
+        })
+    }
+
+    /// Returns the id of the user the token was issued to.
+    ///
+    /// The signature, audience, issuer and expiry of the token are checked.
+    pub async fn verify(&self, token: &str) -> Result<Uuid, ServerError> {
+        let (iss, sub) = match self {
+            TokenVerifier::Firebase(verifier) => match verifier.verify_token(token).await {
+                Ok(token) => (token.critical_claims.iss, token.critical_claims.sub),
+                Err(err) => {
+                    warn!("Token is invalid because {err}!");
+                    return Err(ServerError::Unauthorized("the token is invalid".into()));
+                }
+            },
+            TokenVerifier::Jwks(verifier) => verifier.verify(token).await?,
+        };
+
+        if sub.trim().is_empty() {
+            warn!("Token of {iss} has no subject!");
+            return Err(ServerError::Unauthorized(
+                "the token is not issued to a user".into(),
+            ));
         }
-        Err(err) => {
-            warn!("Token is invalid because {err}!");
-            Err(anyhow!("Your token is incorrect!").into())
+
+        let user_id = user_id(&iss, &sub);
+        info!("Token for user {user_id} is valid!");
+
+        Ok(user_id)
+    }
+}
+
+/// The id of the user the issuer knows as `subject`.
+///
+/// A subject that is a UUID, like the ones of Cognito and the local issuer, is the id itself.
+/// Any other, like the 28-character uids of Firebase, is mapped to a UUID derived from
+/// the issuer and the subject, so the user gets the same id on every login.
+pub fn user_id(issuer: &str, subject: &str) -> Uuid {
+    if let Ok(id) = subject.parse() {
+        return id;
+    }
+
+    let digest = Sha256::new()
+        .chain_update(issuer)
+        .chain_update([0])
+        .chain_update(subject)
+        .finalize();
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&digest[..16]);
+
+    Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// The keys of a JWKS along with when they were fetched.
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Option<Instant>,
+}
+
+/// Verifies the tokens signed with the keys published as a JWKS.
+///
+/// The keys are cached for [`JWKS_TTL`] and fetched again earlier when a token
+/// is signed with a key that is not cached, which is how the issuer rotates them.
+pub struct JwksVerifier {
+    /// Where the keys are fetched from, `None` if they are fixed.
+    jwks_url: Option<String>,
+    validation: Validation,
+    http: reqwest::Client,
+    cache: RwLock<CachedKeys>,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+}
+
+impl JwksVerifier {
+    pub fn new(jwks_url: String, issuer: &str, audience: &str) -> Self {
+        Self::build(Some(jwks_url), None, issuer, audience)
+    }
+
+    /// Verifies the tokens with the given keys only, they are never fetched.
+    pub fn with_keys(keys: JwkSet, issuer: &str, audience: &str) -> Self {
+        Self::build(None, Some(keys), issuer, audience)
+    }
+
+    fn build(jwks_url: Option<String>, keys: Option<JwkSet>, issuer: &str, audience: &str) -> Self {
+        // the algorithm is the one of the key the token is signed with, see `decoding_key`
+        let mut validation = Validation::default();
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        Self {
+            jwks_url,
+            validation,
+            http: reqwest::Client::new(),
+            cache: RwLock::new(CachedKeys {
+                fetched_at: keys.as_ref().map(|_| Instant::now()),
+                keys: keys.unwrap_or(JwkSet { keys: vec![] }),
+            }),
         }
     }
+
+    /// Returns the issuer and the subject of the token.
+    pub async fn verify(&self, token: &str) -> Result<(String, String), ServerError> {
+        let header = decode_header(token).map_err(invalid_token)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| ServerError::Unauthorized("the token has no key id".into()))?;
+
+        let (key, algorithm) = self.decoding_key(&kid).await?;
+
+        let mut validation = self.validation.clone();
+        validation.algorithms = vec![algorithm];
+
+        let claims = decode::<Claims>(token, &key, &validation).map_err(invalid_token)?;
+
+        Ok((claims.claims.iss, claims.claims.sub))
+    }
+
+    async fn decoding_key(&self, kid: &str) -> Result<(DecodingKey, Algorithm), ServerError> {
+        let Some(jwks_url) = &self.jwks_url else {
+            let cache = self.cache.read().await;
+            let jwk = cache.keys.find(kid).ok_or_else(|| {
+                ServerError::Unauthorized("the token is signed with an unknown key".into())
+            })?;
+
+            return decoding_key(jwk);
+        };
+
+        {
+            let cache = self.cache.read().await;
+            let fresh = cache.fetched_at.is_some_and(|at| at.elapsed() < JWKS_TTL);
+
+            if let (true, Some(jwk)) = (fresh, cache.keys.find(kid)) {
+                return decoding_key(jwk);
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+
+        // the keys could have been fetched while waiting for the lock
+        let refresh = match cache.fetched_at {
+            None => true,
+            Some(at) if at.elapsed() >= JWKS_TTL => true,
+            Some(at) => cache.keys.find(kid).is_none() && at.elapsed() >= JWKS_MIN_REFRESH_INTERVAL,
+        };
+
+        if refresh {
+            match self.fetch_keys(jwks_url).await {
+                Ok(keys) => {
+                    *cache = CachedKeys {
+                        keys,
+                        fetched_at: Some(Instant::now()),
+                    };
+                }
+                // the issuer being down shouldn't log everyone out
+                Err(e) if cache.fetched_at.is_some() => {
+                    warn!("couldn't refresh the keys, using the cached ones: {e:?}");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let jwk = cache.keys.find(kid).ok_or_else(|| {
+            ServerError::Unauthorized("the token is signed with an unknown key".into())
+        })?;
+
+        decoding_key(jwk)
+    }
+
+    async fn fetch_keys(&self, jwks_url: &str) -> Result<JwkSet, ServerError> {
+        let body = self
+            .http
+            .get(jwks_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch the keys from {jwks_url}"))?
+            .text()
+            .await?;
+
+        Ok(serde_json::from_str(&body)
+            .with_context(|| format!("Failed to parse the keys from {jwks_url}"))?)
+    }
 }
 
-// TODO: THIS SHOULD BE CHANGED TO AWS COGNITO
-pub async fn verify_jwt(token: &str, state: &ServerState) -> Result<Uuid, ServerError> {
-    // verify_token(token, state.live_token_verifier.as_ref()).await
-    todo!()
+/// Only asymmetric keys are accepted, a shared secret can't be published.
+///
+/// The algorithm is the one the key is published for, or the default one of its type,
+/// so that a token can't choose how it's verified.
+fn decoding_key(jwk: &Jwk) -> Result<(DecodingKey, Algorithm), ServerError> {
+    let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+        (AlgorithmParameters::RSA(_), None | Some(KeyAlgorithm::RS256)) => Algorithm::RS256,
+        (AlgorithmParameters::RSA(_), Some(KeyAlgorithm::RS384)) => Algorithm::RS384,
+        (AlgorithmParameters::RSA(_), Some(KeyAlgorithm::RS512)) => Algorithm::RS512,
+        (AlgorithmParameters::RSA(_), Some(KeyAlgorithm::PS256)) => Algorithm::PS256,
+        (AlgorithmParameters::RSA(_), Some(KeyAlgorithm::PS384)) => Algorithm::PS384,
+        (AlgorithmParameters::RSA(_), Some(KeyAlgorithm::PS512)) => Algorithm::PS512,
+        (AlgorithmParameters::EllipticCurve(key), None | Some(KeyAlgorithm::ES256))
+            if key.curve == EllipticCurve::P256 =>
+        {
+            Algorithm::ES256
+        }
+        (AlgorithmParameters::EllipticCurve(key), None | Some(KeyAlgorithm::ES384))
+            if key.curve == EllipticCurve::P384 =>
+        {
+            Algorithm::ES384
+        }
+        _ => {
+            return Err(ServerError::Unauthorized(
+                "the token is signed with an unsupported key".into(),
+            ))
+        }
+    };
+
+    let key = DecodingKey::from_jwk(jwk).context("Failed to read the key of the token")?;
+
+    Ok((key, algorithm))
+}
+
+fn invalid_token(err: jsonwebtoken::errors::Error) -> ServerError {
+    warn!("Token is invalid because {err}!");
+
+    ServerError::Unauthorized(
+        match err.kind() {
+            ErrorKind::ExpiredSignature => "the token has expired",
+            ErrorKind::InvalidAudience | ErrorKind::InvalidIssuer => {
+                "the token is issued for another service"
+            }
+            _ => "the token is invalid",
+        }
+        .into(),
+    )
 }
@@ -0,0 +1,59 @@
+//! Checks the signer of a Diia signature against the documents of the party.
+//!
+//! ```sh
+//! cargo test --test eusign
+//! ```
+
+use kaze_backend::utils::{
+    eusign::{check_signer, DocumentUnit},
+    server_error::ServerError,
+};
+
+const SIGNER: &str = "Шевченко Тарас Григорович";
+
+fn party(taxpayer_number: &str) -> DocumentUnit {
+    let mut unit = DocumentUnit::default();
+    let passport = &mut unit.internal_passport;
+    passport.taxpayer_number = taxpayer_number.into();
+    passport.last_name_ua = "Шевченко".into();
+    passport.first_name_ua = "Тарас".into();
+    passport.middle_name_ua = "Григорович".into();
+    unit
+}
+
+#[test]
+fn signer_of_the_party_is_accepted() {
+    assert!(check_signer(SIGNER, " 1234567890 ", &party("1234567890")).is_ok());
+    assert!(check_signer(
+        "шевченко  тарас григорович",
+        "1234567890",
+        &party("1234567890")
+    )
+    .is_ok());
+}
+
+#[test]
+fn another_signer_is_rejected() {
+    assert!(matches!(
+        check_signer(SIGNER, "0987654321", &party("1234567890")),
+        Err(ServerError::BadRequest(_))
+    ));
+    assert!(matches!(
+        check_signer("Франко Іван Якович", "1234567890", &party("1234567890")),
+        Err(ServerError::BadRequest(_))
+    ));
+}
+
+/// A certificate without a tax number isn't taken for the one of a party without it.
+#[test]
+fn missing_tax_numbers_never_match() {
+    for (signer, party_number) in [("", "1234567890"), ("1234567890", ""), ("", ""), (" ", " ")] {
+        assert!(
+            matches!(
+                check_signer(SIGNER, signer, &party(party_number)),
+                Err(ServerError::BadRequest(_))
+            ),
+            "{signer:?} is accepted for {party_number:?}"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Renders the agreement templates from `resources/typst/templates` with the fonts
+//! from `./fonts`, the way the server does.
+//!
+//! ```sh
+//! cargo test --test render
+//! ```
+
+use std::{future::Future, sync::LazyLock};
+
+use chrono::{DateTime, TimeZone, Utc};
+use kaze_backend::utils::{
+    agreement::{generate, AgreementInputs, DocumentInfo, AGREEMENT_DATA_PATH},
+    config::TypstConfig,
+    templates::{Template, TemplateId, TemplateRegistry},
+};
+use proptest::prelude::*;
+use sha2::{Digest, Sha256};
+use typst::layout::{Frame, FrameItem};
+
+const TEMPLATES_DIR: &str = "resources/typst/templates";
+
+static REGISTRY: LazyLock<TemplateRegistry> = LazyLock::new(|| {
+    block_on(TemplateRegistry::load(
+        TEMPLATES_DIR,
+        TypstConfig::default(),
+    ))
+    .expect("the templates must load")
+});
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("a runtime")
+        .block_on(future)
+}
+
+fn document_info(template: &Template, created_at: DateTime<Utc>) -> DocumentInfo {
+    DocumentInfo {
+        agreement_id: "00000000-0000-0000-0000-000000000000".into(),
+        template_id: template.id,
+        template_version: template.version,
+        created_at,
+    }
+}
+
+/// Renders the agreement the way it's re-rendered from what's stored in the database:
+/// the inputs as JSON, the template version and the creation moment.
+fn render_stored(
+    registry: &TemplateRegistry,
+    template_version: i32,
+    created_at: DateTime<Utc>,
+    inputs: &serde_json::Value,
+) -> Vec<u8> {
+    let template = registry
+        .get(TemplateId::ResidentialLease, template_version)
+        .unwrap();
+    let info = document_info(&template, created_at);
+    let inputs: AgreementInputs = serde_json::from_value(inputs.clone()).unwrap();
+
+    template
+        .render(&generate(inputs, 7, &info).unwrap(), &info)
+        .unwrap()
+}
+
+/// The same stored agreement is rendered into the same bytes every time, so that the
+/// hash signed in Diia stays valid, also after the templates are reloaded.
+#[test]
+fn stored_agreement_is_rendered_into_the_same_bytes() {
+    let version = REGISTRY
+        .latest(TemplateId::ResidentialLease)
+        .unwrap()
+        .version;
+    let created_at = Utc.with_ymd_and_hms(2024, 8, 10, 9, 30, 0).unwrap();
+    let mut inputs = AgreementInputs::default();
+    inputs.rent_data.allowed_animals = vec!["Кіт".into()];
+    let inputs = serde_json::to_value(&inputs).unwrap();
+
+    let first = render_stored(&REGISTRY, version, created_at, &inputs);
+    let second = render_stored(&REGISTRY, version, created_at, &inputs);
+    assert!(first == second, "the second render differs");
+
+    let registry = block_on(TemplateRegistry::load(
+        TEMPLATES_DIR,
+        TypstConfig::default(),
+    ))
+    .unwrap();
+    block_on(registry.reload()).unwrap();
+    let reloaded = render_stored(&registry, version, created_at, &inputs);
+    assert!(first == reloaded, "the render after a reload differs");
+
+    assert_eq!(Sha256::digest(&first), Sha256::digest(&second));
+    assert_eq!(Sha256::digest(&first), Sha256::digest(&reloaded));
+}
+
+/// Every kind of agreement has a template, and its latest version renders the fixture data.
+#[test]
+fn every_template_is_rendered() {
+    let created_at = Utc.with_ymd_and_hms(2024, 8, 10, 9, 30, 0).unwrap();
+
+    for id in TemplateId::ALL.into_iter().filter(|id| id.is_agreement()) {
+        let template = REGISTRY
+            .latest(id)
+            .unwrap_or_else(|e| panic!("no template for {id}: {e:?}"));
+        let info = document_info(&template, created_at);
+
+        let data = generate(AgreementInputs::default(), 1, &info).unwrap();
+        let pdf = template
+            .render(&data, &info)
+            .unwrap_or_else(|e| panic!("template {id} can't be rendered: {e:?}"));
+        assert!(
+            pdf.starts_with(b"%PDF"),
+            "template {id} isn't rendered into a PDF"
+        );
+    }
+}
+
+/// Appends the text of the frame, in the order it's laid out.
+fn collect_text(frame: &Frame, text: &mut String) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => collect_text(&group.frame, text),
+            FrameItem::Text(item) => text.push_str(&item.text),
+            _ => {}
+        }
+    }
+}
+
+/// Drops what the layout is free to change: spaces and the hyphens of broken words.
+fn without_layout(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect()
+}
+
+/// Text a user may enter, mixing the syntax of Typst with the letters and symbols
+/// the bundled fonts have glyphs for. The characters without a glyph are dropped by
+/// the layout, so they can't be compared.
+fn user_text() -> impl Strategy<Value = String> {
+    let fragment = prop_oneof![
+        Just("\"".to_owned()),
+        Just("\\".to_owned()),
+        Just("#".to_owned()),
+        Just("datetime(".to_owned()),
+        Just("#datetime(year: 2024)".to_owned()),
+        Just("\")#panic(\"".to_owned()),
+        Just("*_`$<>@=[]{}~".to_owned()),
+        "[ -~À-ÿЀ-џҐґΑ-ΡΣ-Ωα-ω«»“”‘’—–№€…]{1,12}",
+    ];
+
+    prop::collection::vec(fragment, 1..5).prop_map(|fragments| fragments.concat())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// Whatever the users enter is rendered as literal text, never as Typst markup or code.
+    #[test]
+    fn user_input_is_rendered_literally(
+        destination in user_text(),
+        animal in user_text(),
+        record_number in user_text(),
+    ) {
+        let template = REGISTRY.latest(TemplateId::ResidentialLease).unwrap();
+        let info = document_info(&template, Utc::now());
+
+        let mut inputs = AgreementInputs::default();
+        inputs.rent_data.destination = destination.clone();
+        inputs.rent_data.allowed_animals = vec![animal.clone()];
+        inputs.ownership_data.record_number = record_number.clone();
+
+        let data = generate(inputs, 1, &info).unwrap();
+        let document = template
+            .world(info.created_at)
+            .with_virtual_file(AGREEMENT_DATA_PATH, serde_json::to_vec(&data).unwrap())
+            .compile()
+            .unwrap();
+
+        let mut text = String::new();
+        for page in &document.pages {
+            collect_text(&page.frame, &mut text);
+        }
+        let text = without_layout(&text);
+
+        for input in [&destination, &animal, &record_number] {
+            prop_assert!(
+                text.contains(&without_layout(input)),
+                "{input:?} isn't rendered literally"
+            );
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Maps the subjects of the tokens to the ids of the users.
+//!
+//! ```sh
+//! cargo test --test verify_jwt
+//! ```
+
+use kaze_backend::utils::verify_jwt::user_id;
+use uuid::Uuid;
+
+const FIREBASE: &str = "https://securetoken.google.com/kaze";
+
+#[test]
+fn uuid_subject_is_the_user_id() {
+    let id = Uuid::new_v4();
+    assert_eq!(user_id(FIREBASE, &id.to_string()), id);
+}
+
+/// Firebase uids aren't UUIDs, every one still gets its own id, the same on every login.
+#[test]
+fn other_subjects_are_mapped_to_a_stable_id() {
+    let uid = "Xq3fY8kLm2PzR7tVw9bN4cJd1Ea5";
+
+    assert_eq!(user_id(FIREBASE, uid), user_id(FIREBASE, uid));
+    assert_ne!(
+        user_id(FIREBASE, uid),
+        user_id(FIREBASE, "Xq3fY8kLm2PzR7tVw9bN4cJd1Ea6")
+    );
+    assert_ne!(
+        user_id(FIREBASE, uid),
+        user_id("https://securetoken.google.com/other", uid)
+    );
+}